@@ -0,0 +1,32 @@
+// white (or whatever `color` is) in the shape of the sprite's opaque pixels, drawn over the
+// sprite with `amount` as its opacity, so the sprite mixes towards the flash colour.
+#import bevy_sprite::{
+    mesh2d_vertex_output::VertexOutput,
+    mesh2d_view_bindings::view,
+}
+
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping
+#endif
+
+struct HitFlash {
+    color: vec4<f32>,
+    // min uv and size of the sprite's frame within its image
+    uv_rect: vec4<f32>,
+    amount: f32,
+};
+
+@group(2) @binding(0) var<uniform> material: HitFlash;
+@group(2) @binding(1) var texture: texture_2d<f32>;
+@group(2) @binding(2) var texture_sampler: sampler;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    let uv = material.uv_rect.xy + mesh.uv * material.uv_rect.zw;
+    let coverage = textureSample(texture, texture_sampler, uv).a;
+    var output_color = vec4(material.color.rgb, coverage * material.amount);
+#ifdef TONEMAP_IN_SHADER
+    output_color = tonemapping::tone_mapping(output_color, view.color_grading);
+#endif
+    return output_color;
+}
@@ -211,5 +211,5 @@ pub fn _setup_progress_bar(mut commands: Commands, asset_server: Res<AssetServer
         .with_children(|button| {
             button.spawn(Text::new("Progress Bar"));
         });
-    println!("progress bar added");
+    debug!("progress bar added");
 }
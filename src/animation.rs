@@ -0,0 +1,372 @@
+use bevy::asset::LoadedFolder;
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::schedule::GameSet;
+
+// sprite-sheet animation driven by clip definitions in assets/clips.anim.toml. A clip's frames
+// either come from a grid sheet or from a folder of single images that gets packed into an
+// atlas at load time.
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaybackMode {
+    /// Plays through once and holds the last frame.
+    #[default]
+    Once,
+    Loop,
+    /// Plays forwards then backwards, forever.
+    PingPong,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ClipEventDef {
+    pub frame: usize,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ClipSource {
+    Sheet {
+        sheet: PathBuf,
+        tile_size: [u32; 2],
+        columns: u32,
+        rows: u32,
+        /// Number of used tiles, for sheets whose last row isn't full.
+        frames: Option<usize>,
+    },
+    Folder {
+        folder: PathBuf,
+    },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ClipDef {
+    #[serde(flatten)]
+    pub source: ClipSource,
+    pub fps: f32,
+    #[serde(default)]
+    pub mode: PlaybackMode,
+    #[serde(default)]
+    pub events: Vec<ClipEventDef>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PlayerAnimations {
+    /// Clip played every time the turret fires, the turret sprite is static when unset.
+    pub fire_clip: Option<String>,
+    /// Turret scale while it shows the fire clip's art, which is drawn at another size than
+    /// the static sprite.
+    pub scale: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Asset, TypePath)]
+pub struct AnimationLibrary {
+    pub clips: HashMap<String, ClipDef>,
+    #[serde(default)]
+    pub player: PlayerAnimations,
+}
+
+#[derive(Resource)]
+pub struct AnimationLibraryHandle(pub Handle<AnimationLibrary>);
+
+/// The atlas a clip plays from once its images are loaded.
+#[derive(Clone)]
+pub struct ClipAtlas {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub frames: usize,
+}
+
+#[derive(Resource, Default)]
+pub struct ClipAtlases {
+    pub ready: HashMap<String, ClipAtlas>,
+    // folder clips waiting for every frame image to load
+    pending_folders: HashMap<String, Handle<LoadedFolder>>,
+    failed: HashSet<String>,
+}
+
+impl ClipAtlases {
+    /// True when `clip`'s frames couldn't be packed, it never gets an atlas.
+    pub fn has_failed(&self, clip: &str) -> bool {
+        self.failed.contains(clip)
+    }
+}
+
+/// Plays a clip from the [`AnimationLibrary`] on the entity's `Sprite`.
+#[derive(Component)]
+pub struct SpriteAnimation {
+    pub clip: String,
+    pub frame: usize,
+    pub playing: bool,
+    forward: bool,
+    // set by play() so the first frame's events go out on the next update
+    just_started: bool,
+    timer: Timer,
+}
+
+impl SpriteAnimation {
+    /// A stopped animation showing the first frame of `clip`.
+    pub fn new(clip: impl Into<String>) -> Self {
+        Self {
+            clip: clip.into(),
+            frame: 0,
+            playing: false,
+            forward: true,
+            just_started: false,
+            timer: Timer::default(),
+        }
+    }
+
+    /// (Re)starts the clip from its first frame.
+    pub fn play(&mut self) {
+        self.frame = 0;
+        self.forward = true;
+        self.playing = true;
+        self.just_started = true;
+        self.timer.reset();
+    }
+}
+
+/// Sent when an animation reaches a frame that has a named event in its clip definition
+/// (e.g. the "muzzle_flash" frame of a fire clip).
+#[allow(dead_code)]
+#[derive(Event, Debug, Clone)]
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub clip: String,
+    pub name: String,
+}
+
+/// Frame that comes after `frame` for a clip of `frames` frames, `None` when a `Once` clip has
+/// finished. `forward` is flipped in place when a ping-pong clip turns around.
+pub fn next_frame(
+    mode: PlaybackMode,
+    frame: usize,
+    frames: usize,
+    forward: &mut bool,
+) -> Option<usize> {
+    let last = frames.saturating_sub(1);
+    match mode {
+        PlaybackMode::Once => (frame < last).then_some(frame + 1),
+        PlaybackMode::Loop => Some(if frame >= last { 0 } else { frame + 1 }),
+        PlaybackMode::PingPong => {
+            if last == 0 {
+                return Some(0);
+            }
+            if *forward && frame >= last {
+                *forward = false;
+            } else if !*forward && frame == 0 {
+                *forward = true;
+            }
+            Some(if *forward { frame + 1 } else { frame - 1 })
+        }
+    }
+}
+
+pub fn load_animation_library(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(AnimationLibraryHandle(asset_server.load("clips.anim.toml")));
+}
+
+/// Turns clip definitions into atlases: grid sheets straight away, frame folders once all
+/// their images are in.
+pub fn build_clip_atlases(
+    library_handle: Res<AnimationLibraryHandle>,
+    libraries: Res<Assets<AnimationLibrary>>,
+    mut atlases: ResMut<ClipAtlases>,
+    asset_server: Res<AssetServer>,
+    folders: Res<Assets<LoadedFolder>>,
+    mut images: ResMut<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    let Some(library) = libraries.get(&library_handle.0) else {
+        return;
+    };
+
+    for (name, clip) in library.clips.iter() {
+        if atlases.ready.contains_key(name) || atlases.failed.contains(name) {
+            continue;
+        }
+        match &clip.source {
+            ClipSource::Sheet {
+                sheet,
+                tile_size,
+                columns,
+                rows,
+                frames,
+            } => {
+                let layout = TextureAtlasLayout::from_grid(
+                    UVec2::from(*tile_size),
+                    *columns,
+                    *rows,
+                    None,
+                    None,
+                );
+                atlases.ready.insert(
+                    name.clone(),
+                    ClipAtlas {
+                        image: asset_server.load(sheet.clone()),
+                        layout: layouts.add(layout),
+                        frames: frames.unwrap_or((columns * rows) as usize),
+                    },
+                );
+            }
+            ClipSource::Folder { folder } => {
+                let folder_handle = atlases
+                    .pending_folders
+                    .entry(name.clone())
+                    .or_insert_with(|| asset_server.load_folder(folder.clone()))
+                    .clone();
+                if !asset_server.is_loaded_with_dependencies(&folder_handle) {
+                    continue;
+                }
+                let Some(loaded) = folders.get(&folder_handle) else {
+                    continue;
+                };
+                atlases.pending_folders.remove(name);
+                match pack_folder(loaded, &asset_server, &mut images, &mut layouts) {
+                    Some(atlas) => {
+                        atlases.ready.insert(name.clone(), atlas);
+                    }
+                    None => {
+                        warn!("could not pack the frames of animation clip {name}");
+                        atlases.failed.insert(name.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// frames are ordered by file name, the builder keeps insertion order as the atlas index
+fn pack_folder(
+    loaded: &LoadedFolder,
+    asset_server: &AssetServer,
+    images: &mut Assets<Image>,
+    layouts: &mut Assets<TextureAtlasLayout>,
+) -> Option<ClipAtlas> {
+    let mut frames: Vec<Handle<Image>> = loaded
+        .handles
+        .iter()
+        .filter_map(|handle| handle.clone().try_typed::<Image>().ok())
+        .collect();
+    frames.sort_by_key(|handle| asset_server.get_path(handle.id()).map(|p| p.to_string()));
+
+    let mut builder = TextureAtlasBuilder::default();
+    builder.max_size(UVec2::splat(8192));
+    for frame in frames.iter() {
+        builder.add_texture(Some(frame.id()), images.get(frame)?);
+    }
+    let (layout, _sources, image) = builder.build().ok()?;
+
+    Some(ClipAtlas {
+        image: images.add(image),
+        layout: layouts.add(layout),
+        frames: frames.len(),
+    })
+}
+
+/// Swaps an animated entity's sprite over to its clip's atlas once the atlas exists.
+pub fn apply_clip_atlases(
+    mut query: Query<(&SpriteAnimation, &mut Sprite)>,
+    atlases: Res<ClipAtlases>,
+) {
+    for (animation, mut sprite) in query.iter_mut() {
+        if sprite.texture_atlas.is_some() {
+            continue;
+        }
+        let Some(atlas) = atlases.ready.get(&animation.clip) else {
+            continue;
+        };
+        sprite.image = atlas.image.clone();
+        sprite.texture_atlas = Some(TextureAtlas {
+            layout: atlas.layout.clone(),
+            index: animation.frame,
+        });
+    }
+}
+
+pub fn advance_animations(
+    time: Res<Time>,
+    library_handle: Res<AnimationLibraryHandle>,
+    libraries: Res<Assets<AnimationLibrary>>,
+    atlases: Res<ClipAtlases>,
+    mut query: Query<(Entity, &mut SpriteAnimation, &mut Sprite)>,
+    mut events: EventWriter<AnimationEvent>,
+) {
+    let Some(library) = libraries.get(&library_handle.0) else {
+        return;
+    };
+
+    for (entity, mut animation, mut sprite) in query.iter_mut() {
+        let (Some(clip), Some(atlas)) = (
+            library.clips.get(&animation.clip),
+            atlases.ready.get(&animation.clip),
+        ) else {
+            continue;
+        };
+        if !animation.playing {
+            continue;
+        }
+
+        let frame_time = 1.0 / clip.fps.max(f32::EPSILON);
+        if animation.timer.duration().as_secs_f32() != frame_time {
+            animation.timer = Timer::from_seconds(frame_time, TimerMode::Repeating);
+        }
+        animation.timer.tick(time.delta());
+
+        let clip_name = animation.clip.clone();
+        let mut send_frame_events = |frame: usize| {
+            for event in clip.events.iter().filter(|e| e.frame == frame) {
+                events.write(AnimationEvent {
+                    entity,
+                    clip: clip_name.clone(),
+                    name: event.name.clone(),
+                });
+            }
+        };
+        if animation.just_started {
+            animation.just_started = false;
+            send_frame_events(animation.frame);
+        }
+        for _ in 0..animation.timer.times_finished_this_tick() {
+            let mut forward = animation.forward;
+            match next_frame(clip.mode, animation.frame, atlas.frames, &mut forward) {
+                Some(frame) => {
+                    animation.frame = frame;
+                    animation.forward = forward;
+                    send_frame_events(frame);
+                }
+                None => {
+                    animation.playing = false;
+                    break;
+                }
+            }
+        }
+
+        if let Some(texture_atlas) = &mut sprite.texture_atlas {
+            texture_atlas.index = animation.frame;
+        }
+    }
+}
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<AnimationLibrary>()
+            .init_asset_loader::<crate::asset_loader::AnimationLibraryLoader>()
+            .init_resource::<ClipAtlases>()
+            .add_event::<AnimationEvent>()
+            .add_systems(Startup, load_animation_library)
+            .add_systems(
+                Update,
+                (build_clip_atlases, apply_clip_atlases, advance_animations)
+                    .chain()
+                    .in_set(GameSet::Presentation),
+            );
+    }
+}
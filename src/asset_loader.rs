@@ -1,7 +1,10 @@
 use bevy::asset::io::Reader;
 use bevy::asset::{AssetLoader, LoadContext};
 
+use crate::animation::AnimationLibrary;
+use crate::bullet_pattern::PatternLibrary;
 use crate::config::Config;
+use crate::particles::EffectPresets;
 
 #[derive(Default)]
 pub struct ConfigLoader;
@@ -27,3 +30,79 @@ impl AssetLoader for ConfigLoader {
         &["toml"]
     }
 }
+
+#[derive(Default)]
+pub struct AnimationLibraryLoader;
+
+impl AssetLoader for AnimationLibraryLoader {
+    type Asset = AnimationLibrary;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let library: AnimationLibrary = toml::from_slice(&bytes)?;
+        Ok(library)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.toml"]
+    }
+}
+
+#[derive(Default)]
+pub struct EffectPresetsLoader;
+
+impl AssetLoader for EffectPresetsLoader {
+    type Asset = EffectPresets;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let mut presets: EffectPresets = toml::from_slice(&bytes)?;
+        presets.fix_ranges();
+        Ok(presets)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["fx.toml"]
+    }
+}
+
+#[derive(Default)]
+pub struct PatternLibraryLoader;
+
+impl AssetLoader for PatternLibraryLoader {
+    type Asset = PatternLibrary;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let library: PatternLibrary = toml::from_slice(&bytes)?;
+        Ok(library)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bullets.toml"]
+    }
+}
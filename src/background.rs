@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use rand::Rng;
+use std::path::PathBuf;
+
+use crate::components_and_resources::{ConfigHandle, Level};
+use crate::config::Config;
+use crate::rng::{GameRng, RngStream};
+use crate::schedule::GameSet;
+
+// layered parallax background: the level's space art far back, two procedural star layers in
+// front of it. Each layer follows the camera by (1 - depth) so it lags behind camera movement
+// (lead, shake) a little less the further away it is.
+
+// how much bigger than the window the backdrop is drawn, leaves room for drift and parallax
+const BACKDROP_OVERSCAN: f32 = 1.15;
+const BACKDROP_WOBBLE: Vec2 = Vec2::new(40.0, 25.0);
+// seconds for one full backdrop wobble
+const BACKDROP_WOBBLE_PERIOD: f32 = 90.0;
+const DEFAULT_BACKGROUND: &str = "SpaceBackground2.png";
+
+#[derive(Component)]
+pub struct ParallaxLayer {
+    /// 0.0 moves with the camera (infinitely far), 1.0 stays fixed in the world.
+    pub depth: f32,
+    pub offset: Vec2,
+}
+
+#[derive(Component)]
+pub struct Backdrop;
+
+/// A wrapping layer of generated stars that scrolls at `drift` pixels per second.
+#[derive(Component)]
+pub struct StarField {
+    pub drift: Vec2,
+    scroll: Vec2,
+}
+
+#[derive(Component)]
+pub struct Star {
+    // position inside the field as a fraction of its size, so the field fits any window
+    base: Vec2,
+    brightness: f32,
+    twinkle_speed: f32,
+    phase: f32,
+}
+
+struct StarLayerDef {
+    depth: f32,
+    drift: Vec2,
+    count: usize,
+    size: [f32; 2],
+    brightness: [f32; 2],
+    z: f32,
+}
+
+const STAR_LAYERS: [StarLayerDef; 2] = [
+    StarLayerDef {
+        depth: 0.08,
+        drift: Vec2::new(-4.0, -1.5),
+        count: 140,
+        size: [1.0, 2.0],
+        brightness: [0.25, 0.6],
+        z: -4.5,
+    },
+    StarLayerDef {
+        depth: 0.2,
+        drift: Vec2::new(-10.0, -3.5),
+        count: 50,
+        size: [2.0, 3.5],
+        brightness: [0.5, 1.0],
+        z: -4.0,
+    },
+];
+
+fn level_background(config: Option<&Config>, level: u32) -> PathBuf {
+    let Some(config) = config else {
+        return PathBuf::from(DEFAULT_BACKGROUND);
+    };
+    let backgrounds = &config.assets.backgrounds;
+    if backgrounds.is_empty() {
+        config.assets.background.clone()
+    } else {
+        backgrounds[level as usize % backgrounds.len()].clone()
+    }
+}
+
+pub fn setup_background(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    commands.spawn((
+        Sprite::from_image(asset_server.load(DEFAULT_BACKGROUND)),
+        Transform::from_xyz(0.0, 0.0, -5.0),
+        ParallaxLayer {
+            depth: 0.02,
+            offset: Vec2::ZERO,
+        },
+        Backdrop,
+    ));
+
+    let rng = game_rng.stream(RngStream::Background);
+    for layer in STAR_LAYERS.iter() {
+        commands
+            .spawn((
+                Transform::from_xyz(0.0, 0.0, layer.z),
+                Visibility::default(),
+                ParallaxLayer {
+                    depth: layer.depth,
+                    offset: Vec2::ZERO,
+                },
+                StarField {
+                    drift: layer.drift,
+                    scroll: Vec2::ZERO,
+                },
+            ))
+            .with_children(|parent| {
+                for _ in 0..layer.count {
+                    let size = rng.random_range(layer.size[0]..=layer.size[1]);
+                    let brightness = rng.random_range(layer.brightness[0]..=layer.brightness[1]);
+                    parent.spawn((
+                        Sprite::from_color(Color::WHITE, Vec2::splat(size)),
+                        Transform::default(),
+                        Star {
+                            base: Vec2::new(rng.random(), rng.random()),
+                            brightness,
+                            twinkle_speed: rng.random_range(0.5..3.0),
+                            phase: rng.random_range(0.0..std::f32::consts::TAU),
+                        },
+                    ));
+                }
+            });
+    }
+}
+
+/// Swaps the backdrop art when the level changes (or once the config has loaded).
+pub fn select_level_background(
+    mut q_backdrop: Query<&mut Sprite, With<Backdrop>>,
+    level: Res<Level>,
+    config_handle: Res<ConfigHandle>,
+    configs: Res<Assets<Config>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(mut sprite) = q_backdrop.single_mut() else {
+        return;
+    };
+    let wanted = level_background(configs.get(&config_handle.0), level.0);
+    let current = asset_server
+        .get_path(sprite.image.id())
+        .map(|path| path.path().to_path_buf());
+    if current.as_ref() != Some(&wanted) {
+        sprite.image = asset_server.load(wanted);
+    }
+}
+
+/// Scales the backdrop to cover the window whatever the art's resolution, and wobbles it slowly.
+pub fn fit_backdrop(
+    mut q_backdrop: Query<(&Sprite, &mut Transform, &mut ParallaxLayer), With<Backdrop>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    images: Res<Assets<Image>>,
+    time: Res<Time>,
+) {
+    let (Ok((sprite, mut transform, mut layer)), Ok(win)) =
+        (q_backdrop.single_mut(), q_window.single())
+    else {
+        return;
+    };
+    if let Some(image) = images.get(&sprite.image) {
+        let cover = win.size() * BACKDROP_OVERSCAN / image.size_f32();
+        transform.scale = Vec3::splat(cover.max_element());
+    }
+    let t = time.elapsed_secs() / BACKDROP_WOBBLE_PERIOD * std::f32::consts::TAU;
+    layer.offset = Vec2::new(t.sin(), (t * 0.7).cos()) * BACKDROP_WOBBLE;
+}
+
+pub fn scroll_star_fields(
+    mut q_field: Query<(&mut StarField, &Children)>,
+    mut q_star: Query<(&Star, &mut Transform, &mut Sprite)>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    time: Res<Time>,
+) {
+    let Ok(win) = q_window.single() else {
+        return;
+    };
+    // a little larger than the window so stars don't pop in at the edges under parallax
+    let field_size = win.size() * BACKDROP_OVERSCAN;
+    let elapsed = time.elapsed_secs();
+
+    for (mut field, children) in q_field.iter_mut() {
+        let drift = field.drift;
+        field.scroll += drift * time.delta_secs();
+        let scroll = field.scroll / field_size;
+        for child in children.iter() {
+            let Ok((star, mut transform, mut sprite)) = q_star.get_mut(child) else {
+                continue;
+            };
+            let wrapped = (star.base + scroll).fract_gl() - Vec2::splat(0.5);
+            transform.translation = (wrapped * field_size).extend(0.0);
+            let twinkle = 0.65 + 0.35 * (elapsed * star.twinkle_speed + star.phase).sin();
+            sprite.color.set_alpha(star.brightness * twinkle);
+        }
+    }
+}
+
+pub fn apply_parallax(
+    mut q_layer: Query<(&ParallaxLayer, &mut Transform), Without<Camera2d>>,
+    q_camera: Query<&Transform, With<Camera2d>>,
+) {
+    let camera = q_camera
+        .single()
+        .map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    for (layer, mut transform) in q_layer.iter_mut() {
+        let position = camera * (1.0 - layer.depth) + layer.offset;
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Level>()
+            .add_systems(Startup, setup_background)
+            .add_systems(
+                Update,
+                (
+                    select_level_background,
+                    fit_backdrop,
+                    scroll_star_fields,
+                    apply_parallax,
+                )
+                    .chain()
+                    .after(crate::camera_fx::update_camera_rig)
+                    .in_set(GameSet::Presentation),
+            );
+    }
+}
@@ -0,0 +1,451 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_rapier2d::prelude::*;
+use std::path::PathBuf;
+
+use crate::bullet_pattern::PatternEmitter;
+use crate::camera_fx::AddTrauma;
+use crate::collision::Layer;
+use crate::components_and_resources::{ConfigHandle, Enemy, Level, Music};
+use crate::config::Config;
+use crate::damage::{DamageRelay, DeathEvent, DeathHook, Health, OnDeath};
+use crate::lighting::{self, Light2d};
+use crate::particles::{self, SoftDotImage};
+use crate::schedule::{GameSet, Interpolated};
+use crate::sprite_collider::SpriteCollider;
+use crate::waves::{WaveStarted, Waves};
+
+// boss fights: an enemy station that sweeps along the top of the screen and fires bullet
+// patterns (patterns.bullets.toml) that get nastier as its health drops. Shots on its glowing
+// weak points do extra damage.
+
+// on-screen width of a boss, whatever the resolution of its art
+const BOSS_DIAMETER: f32 = 320.0;
+// distance from the top of the window to the boss's centre once it has flown in
+const BOSS_TOP_MARGIN: f32 = 200.0;
+const BOSS_ENTRY_SECONDS: f32 = 2.5;
+const BOSS_SWEEP_SPEED: f32 = 0.35;
+const BOSS_SPIN_SPEED: f32 = 0.2;
+const WEAK_POINT_MULTIPLIER: f32 = 3.0;
+const PHASE_CHANGE_TRAUMA: f32 = 0.6;
+const BOSS_MUSIC_SPEED: f32 = 1.15;
+
+struct BossDef {
+    name: &'static str,
+    image: &'static str,
+    // the art's width in pixels, sizes the boss and places its weak points
+    image_size: f32,
+    health: f32,
+    // weak point centres as fractions of the art's half-size
+    weak_points: &'static [Vec2],
+}
+
+// the spaceStation1..4 art, handed out in order and reused once every one has been fought
+const BOSSES: [BossDef; 4] = [
+    BossDef {
+        name: "Derelict Outpost",
+        image: "spaceStation1.png",
+        image_size: 256.0,
+        health: 1500.0,
+        weak_points: &[Vec2::new(0.0, -0.55)],
+    },
+    BossDef {
+        name: "Relay Fortress",
+        image: "spaceStation2.png",
+        image_size: 256.0,
+        health: 2200.0,
+        weak_points: &[Vec2::new(-0.5, -0.3), Vec2::new(0.5, -0.3)],
+    },
+    BossDef {
+        name: "Dread Spire",
+        image: "spaceStation3.png",
+        image_size: 2048.0,
+        health: 3000.0,
+        weak_points: &[
+            Vec2::new(0.0, -0.6),
+            Vec2::new(-0.5, 0.3),
+            Vec2::new(0.5, 0.3),
+        ],
+    },
+    BossDef {
+        name: "Crown of Ash",
+        image: "spaceStation4.png",
+        image_size: 2040.0,
+        health: 4000.0,
+        weak_points: &[
+            Vec2::new(0.0, -0.6),
+            Vec2::new(-0.6, 0.0),
+            Vec2::new(0.6, 0.0),
+            Vec2::new(0.0, 0.6),
+        ],
+    },
+];
+
+struct BossPhase {
+    // the phase starts once health drops to this fraction
+    from_health: f32,
+    // bullet pattern from patterns.bullets.toml
+    pattern: &'static str,
+}
+
+const PHASES: [BossPhase; 3] = [
+    BossPhase {
+        from_health: 1.0,
+        pattern: "boss_rings",
+    },
+    BossPhase {
+        from_health: 0.66,
+        pattern: "boss_spiral",
+    },
+    BossPhase {
+        from_health: 0.33,
+        pattern: "boss_final",
+    },
+];
+
+#[derive(Component)]
+pub struct Boss {
+    pub name: &'static str,
+    /// None while the boss is still flying in.
+    pub phase: Option<usize>,
+    age: f32,
+    /// Where it sweeps around, None for the middle of the top of the screen.
+    anchor: Option<Vec2>,
+}
+
+#[derive(Component)]
+pub struct BossHealthBar;
+
+#[derive(Component)]
+pub struct BossHealthFill;
+
+fn boss_death_hooks() -> OnDeath {
+    OnDeath(vec![
+        DeathHook::Effect("boss_explosion".to_string()),
+        DeathHook::Effect("meteor_debris".to_string()),
+        DeathHook::Flash {
+            light: Light2d {
+                color: Color::srgb(1.0, 0.7, 0.3),
+                radius: 600.0,
+                intensity: 3.0,
+            },
+            seconds: 0.8,
+        },
+        DeathHook::Sound {
+            path: PathBuf::from("explosion.ogg"),
+            volume: 8.0,
+        },
+        DeathHook::Score(25),
+        DeathHook::Despawn,
+    ])
+}
+
+/// Art of every boss, in the order they are fought.
+pub fn boss_images() -> impl Iterator<Item = &'static str> {
+    BOSSES.iter().map(|def| def.image)
+}
+
+fn boss_top(win: &Window) -> f32 {
+    win.size().y / 2.0 - BOSS_TOP_MARGIN
+}
+
+pub fn spawn_boss(
+    mut commands: Commands,
+    mut started: EventReader<WaveStarted>,
+    mut waves: ResMut<Waves>,
+    level: Res<Level>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    asset_server: Res<AssetServer>,
+    soft_dot: Res<SoftDotImage>,
+) {
+    let Ok(win) = q_window.single() else {
+        return;
+    };
+    for wave in started.read() {
+        if !wave.boss || waves.boss.is_some() {
+            continue;
+        }
+        let (boss, name) = spawn_boss_entity(
+            &mut commands,
+            level.0 as usize,
+            None,
+            win,
+            &asset_server,
+            &soft_dot,
+        );
+        waves.boss = Some(boss);
+        info!("wave {}: boss {} appeared", wave.number, name);
+    }
+}
+
+/// Puts boss `index` (wrapping round) above the window, from where it flies in to the top of
+/// the screen, or to `anchor` when there is one.
+pub fn spawn_boss_entity(
+    commands: &mut Commands,
+    index: usize,
+    anchor: Option<Vec2>,
+    win: &Window,
+    asset_server: &AssetServer,
+    soft_dot: &SoftDotImage,
+) -> (Entity, &'static str) {
+    let def = &BOSSES[index % BOSSES.len()];
+    let half_size = def.image_size / 2.0;
+    let boss = commands
+        .spawn((
+            Sprite::from_image(asset_server.load(def.image)),
+            // starts above the window and flies in
+            Transform::from_xyz(0.0, win.size().y / 2.0 + BOSS_DIAMETER, 0.0)
+                .with_scale(Vec3::splat(BOSS_DIAMETER / def.image_size)),
+            Boss {
+                name: def.name,
+                phase: None,
+                age: 0.0,
+                anchor,
+            },
+            Enemy {
+                direction: Vec3::ZERO,
+                speed: 0.0,
+                enemy_rotation: BOSS_SPIN_SPEED,
+            },
+            Health::new(def.health),
+            boss_death_hooks(),
+            RigidBody::KinematicPositionBased,
+            Interpolated::default(),
+            SpriteCollider,
+            Layer::Enemy.groups(),
+        ))
+        .id();
+    for point in def.weak_points.iter() {
+        let radius = half_size * 0.14;
+        commands.entity(boss).with_child((
+            Sprite {
+                image: soft_dot.0.clone(),
+                color: Color::linear_rgb(3.0, 0.3, 0.2),
+                custom_size: Some(Vec2::splat(radius * 2.5)),
+                ..default()
+            },
+            Transform::from_translation((*point * half_size).extend(0.1)),
+            Enemy {
+                direction: Vec3::ZERO,
+                speed: 0.0,
+                enemy_rotation: 0.0,
+            },
+            DamageRelay {
+                target: boss,
+                multiplier: WEAK_POINT_MULTIPLIER,
+            },
+            Collider::ball(radius),
+            Layer::Enemy.groups(),
+        ));
+    }
+    (boss, def.name)
+}
+
+pub fn move_boss(
+    mut q_boss: Query<(&mut Boss, &mut Transform)>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    time: Res<Time>,
+) {
+    let Ok(win) = q_window.single() else {
+        return;
+    };
+    for (mut boss, mut transform) in q_boss.iter_mut() {
+        boss.age += time.delta_secs();
+        let anchor = boss.anchor.unwrap_or(Vec2::new(0.0, boss_top(win)));
+        let entry = (boss.age / BOSS_ENTRY_SECONDS).min(1.0);
+        let start = win.size().y / 2.0 + BOSS_DIAMETER;
+        // ease out so the boss settles into place
+        let y = start + (anchor.y - start) * (1.0 - (1.0 - entry).powi(3));
+        let x = anchor.x + (boss.age * BOSS_SWEEP_SPEED).sin() * win.size().x * 0.3 * entry;
+        transform.translation = Vec3::new(x, y, transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(boss.age * BOSS_SPIN_SPEED);
+    }
+}
+
+/// Moves the boss to the phase its health calls for, each phase plays its own bullet pattern.
+pub fn update_boss_phase(
+    mut commands: Commands,
+    mut q_boss: Query<(Entity, &mut Boss, &Health, &Transform)>,
+    mut trauma: EventWriter<AddTrauma>,
+) {
+    for (entity, mut boss, health, transform) in q_boss.iter_mut() {
+        // hold fire while flying in
+        if boss.age < BOSS_ENTRY_SECONDS {
+            continue;
+        }
+        let phase = PHASES
+            .iter()
+            .rposition(|phase| health.fraction() <= phase.from_health)
+            .unwrap_or(0);
+        if boss.phase == Some(phase) {
+            continue;
+        }
+        let first = boss.phase.is_none();
+        boss.phase = Some(phase);
+        commands
+            .entity(entity)
+            .insert(PatternEmitter::new(PHASES[phase].pattern));
+        if first {
+            continue;
+        }
+        trauma.write(AddTrauma(PHASE_CHANGE_TRAUMA));
+        particles::spawn_effect(
+            &mut commands,
+            "station_sparks",
+            Transform::from_translation(transform.translation),
+        );
+        lighting::spawn_flash(
+            &mut commands,
+            transform.translation,
+            Light2d {
+                color: Color::srgb(1.0, 0.3, 0.2),
+                radius: 400.0,
+                intensity: 2.5,
+            },
+            0.5,
+        );
+    }
+}
+
+pub fn spawn_boss_health_bar(mut commands: Commands, q_boss: Query<&Boss, Added<Boss>>) {
+    for boss in q_boss.iter() {
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(16.0),
+                    left: Val::Percent(25.0),
+                    width: Val::Percent(50.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(6.0),
+                    ..default()
+                },
+                BossHealthBar,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Text::new(boss.name),
+                    TextFont {
+                        font_size: 32.0,
+                        ..default()
+                    },
+                ));
+                parent
+                    .spawn((
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(14.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                    ))
+                    .with_child((
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.9, 0.2, 0.2)),
+                        BossHealthFill,
+                    ));
+            });
+    }
+}
+
+pub fn update_boss_health_bar(
+    mut commands: Commands,
+    q_boss: Query<&Health, With<Boss>>,
+    q_bar: Query<Entity, With<BossHealthBar>>,
+    mut q_fill: Query<&mut Node, With<BossHealthFill>>,
+) {
+    let Ok(health) = q_boss.single() else {
+        for bar in q_bar.iter() {
+            commands.entity(bar).despawn();
+        }
+        return;
+    };
+    for mut node in q_fill.iter_mut() {
+        node.width = Val::Percent(health.fraction() * 100.0);
+    }
+}
+
+fn play_music(commands: &mut Commands, asset_server: &AssetServer, path: PathBuf, speed: f32) {
+    commands.spawn((
+        AudioPlayer::new(asset_server.load(path)),
+        PlaybackSettings::LOOP.with_speed(speed),
+        Music,
+    ));
+}
+
+/// Switches to the boss track when a boss shows up and back once it's destroyed.
+#[allow(clippy::too_many_arguments)]
+pub fn switch_boss_music(
+    mut commands: Commands,
+    mut started: EventReader<WaveStarted>,
+    mut deaths: EventReader<DeathEvent>,
+    q_boss: Query<(), With<Boss>>,
+    q_music: Query<Entity, With<Music>>,
+    config_handle: Res<ConfigHandle>,
+    configs: Res<Assets<Config>>,
+    asset_server: Res<AssetServer>,
+) {
+    let boss_started = started.read().any(|wave| wave.boss);
+    let boss_died = deaths.read().any(|death| q_boss.contains(death.entity));
+    if boss_started == boss_died {
+        return;
+    }
+    let Some(config) = configs.get(&config_handle.0) else {
+        return;
+    };
+    for music in q_music.iter() {
+        commands.entity(music).despawn();
+    }
+    if boss_started {
+        match &config.assets.boss_music {
+            Some(path) => play_music(&mut commands, &asset_server, path.clone(), 1.0),
+            None => play_music(
+                &mut commands,
+                &asset_server,
+                config.assets.bgmusic.clone(),
+                BOSS_MUSIC_SPEED,
+            ),
+        }
+    } else {
+        play_music(
+            &mut commands,
+            &asset_server,
+            config.assets.bgmusic.clone(),
+            1.0,
+        );
+    }
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                (move_boss, update_boss_phase)
+                    .chain()
+                    .in_set(GameSet::Simulation),
+                (
+                    spawn_boss,
+                    switch_boss_music
+                        .after(crate::damage::apply_damage)
+                        .before(crate::damage::run_death_hooks),
+                )
+                    .after(crate::waves::advance_waves)
+                    .in_set(GameSet::Collision),
+            ),
+        )
+        .add_systems(
+            Update,
+            (spawn_boss_health_bar, update_boss_health_bar)
+                .chain()
+                .in_set(GameSet::Presentation),
+        );
+    }
+}
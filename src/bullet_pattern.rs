@@ -0,0 +1,388 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+
+use crate::collision::{self, Layer};
+use crate::components_and_resources::{Bullet, Player};
+use crate::damage::{DamageDealer, DamageKind};
+use crate::lighting::Light2d;
+use crate::particles::{Curve, ParticleEmitter};
+use crate::pool::EntityPool;
+use crate::schedule::{GameSet, Interpolated};
+use crate::sprite_collider::SpriteCollider;
+
+// scripted enemy fire. Patterns live in assets/patterns.bullets.toml: each one is a timeline of
+// steps that fire rings, fans or other patterns, and a PatternEmitter plays one from its entity.
+// Bullets it fires are ordinary pooled `Bullet`s, move_bullet just follows their speed curve and
+// turn rate.
+
+pub const ENEMY_BULLET_IMAGE: &str = "fireball.png";
+const ENEMY_BULLET_SCALE: f32 = 0.025;
+const ENEMY_BULLET_LIFETIME: f32 = 6.0;
+const ENEMY_BULLET_RANGE: f32 = 3000.0;
+/// Emissive tint for enemy shots, the orange counterpart to the player's bullets.
+const ENEMY_BULLET_EMISSIVE: Color = Color::linear_rgb(3.0, 1.2, 0.4);
+// patterns started by patterns (or by bullets bursting) stop nesting past this depth
+const MAX_NESTING: u32 = 4;
+
+/// A bullet that turns into a pattern of its own `after` seconds into its flight.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BurstDef {
+    pub after: f32,
+    pub pattern: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ProjectileDef {
+    /// `[seconds, pixels per second]` keys over the bullet's flight.
+    pub speed: Curve<2>,
+    /// Degrees per second the bullet's heading turns, curves its path.
+    pub turn: f32,
+    pub damage: f32,
+    pub burst: Option<BurstDef>,
+}
+
+impl Default for ProjectileDef {
+    fn default() -> Self {
+        Self {
+            speed: Curve(vec![[0.0, 200.0]]),
+            turn: 0.0,
+            damage: 8.0,
+            burst: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum Shot {
+    /// `count` bullets evenly around the emitter.
+    Ring { count: u32 },
+    /// `count` bullets fanned over `spread` degrees.
+    Fan { count: u32, spread: f32 },
+    /// Starts another pattern from the emitter's position, facing the step's direction.
+    Pattern { pattern: String },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PatternStep {
+    /// Seconds into the timeline the step first fires.
+    pub at: f32,
+    #[serde(default = "one")]
+    pub times: u32,
+    /// Seconds between repeats.
+    #[serde(default)]
+    pub every: f32,
+    /// Degrees from the pattern's facing (or from the player when `aimed`).
+    #[serde(default)]
+    pub angle: f32,
+    /// Degrees added to `angle` on every repeat, makes spirals.
+    #[serde(default)]
+    pub turn: f32,
+    /// Centre the shot on the player instead of the emitter's facing.
+    #[serde(default)]
+    pub aimed: bool,
+    #[serde(flatten)]
+    pub shot: Shot,
+    /// Overrides the pattern's projectile for this step.
+    pub projectile: Option<ProjectileDef>,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PatternDef {
+    /// Seconds after which the timeline starts over, plays once when unset.
+    pub repeat: Option<f32>,
+    /// Degrees per second the pattern's facing turns.
+    #[serde(default)]
+    pub rotation_speed: f32,
+    #[serde(default)]
+    pub projectile: ProjectileDef,
+    pub timeline: Vec<PatternStep>,
+}
+
+#[derive(Deserialize, Debug, Asset, TypePath)]
+pub struct PatternLibrary {
+    pub patterns: HashMap<String, PatternDef>,
+}
+
+#[derive(Resource)]
+pub struct PatternLibraryHandle(pub Handle<PatternLibrary>);
+
+/// Scripted motion for a bullet fired by a pattern, carried in `Bullet::script`.
+#[derive(Debug, Clone)]
+pub struct BulletScript {
+    pub speed: Curve<2>,
+    /// Radians per second.
+    pub turn: f32,
+    pub burst: Option<BurstDef>,
+    pub age: f32,
+}
+
+/// Plays `pattern` from the entity's global position and rotation.
+#[derive(Component)]
+pub struct PatternEmitter {
+    pub pattern: String,
+    elapsed: f32,
+    rotation: f32,
+    // repeats each timeline step has fired this cycle
+    fired: Vec<u32>,
+    depth: u32,
+}
+
+impl PatternEmitter {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            elapsed: 0.0,
+            rotation: 0.0,
+            fired: Vec::new(),
+            depth: 0,
+        }
+    }
+}
+
+// emitters started by other patterns, removed once their timeline is done
+#[derive(Component)]
+pub struct OneShotPattern;
+
+fn spawn_nested_pattern(
+    commands: &mut Commands,
+    pattern: &str,
+    origin: Vec3,
+    facing: f32,
+    depth: u32,
+) {
+    if depth > MAX_NESTING {
+        warn!("bullet pattern {pattern} nested deeper than {MAX_NESTING}, skipped");
+        return;
+    }
+    commands.spawn((
+        PatternEmitter {
+            depth,
+            ..PatternEmitter::new(pattern)
+        },
+        OneShotPattern,
+        Transform::from_translation(origin).with_rotation(Quat::from_rotation_z(facing)),
+    ));
+}
+
+/// Fires one hostile bullet from the shared bullet pool, moved and retired like the player's.
+pub fn spawn_enemy_bullet(
+    commands: &mut Commands,
+    pool: &mut EntityPool<Bullet>,
+    asset_server: &AssetServer,
+    origin: Vec3,
+    angle: f32,
+    projectile: &ProjectileDef,
+) {
+    let direction = Vec2::from_angle(angle);
+    pool.acquire(commands).insert((
+        Sprite {
+            image: asset_server.load(ENEMY_BULLET_IMAGE),
+            color: ENEMY_BULLET_EMISSIVE,
+            ..default()
+        },
+        Transform::from_translation(origin.truncate().extend(0.5))
+            .with_scale(Vec3::splat(ENEMY_BULLET_SCALE))
+            .with_rotation(Quat::from_rotation_z(angle)),
+        Bullet {
+            speed: projectile.speed.sample(0.0)[1],
+            direction: direction.extend(0.0),
+            lifetime: Timer::from_seconds(ENEMY_BULLET_LIFETIME, TimerMode::Once),
+            range: ENEMY_BULLET_RANGE,
+            travelled: 0.0,
+            script: Some(BulletScript {
+                speed: projectile.speed.clone(),
+                turn: projectile.turn.to_radians(),
+                burst: projectile.burst.clone(),
+                age: 0.0,
+            }),
+        },
+        RigidBody::KinematicPositionBased,
+        Interpolated::default(),
+        SpriteCollider,
+        collision::projectile_physics(Layer::EnemyProjectile),
+        ParticleEmitter::new("enemy_trail"),
+        DamageDealer {
+            amount: projectile.damage,
+            kind: DamageKind::Kinetic,
+        },
+        Light2d {
+            color: Color::srgb(1.0, 0.5, 0.2),
+            radius: 45.0,
+            intensity: 1.5,
+        },
+    ));
+}
+
+pub fn load_pattern_library(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(PatternLibraryHandle(
+        asset_server.load("patterns.bullets.toml"),
+    ));
+}
+
+// repeats of a step due by `elapsed`
+fn repeats_due(step: &PatternStep, elapsed: f32) -> u32 {
+    if elapsed < step.at {
+        return 0;
+    }
+    if step.every <= 0.0 {
+        return step.times;
+    }
+    (((elapsed - step.at) / step.every) as u32 + 1).min(step.times)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_pattern_emitters(
+    mut commands: Commands,
+    mut q_emitter: Query<(
+        Entity,
+        &mut PatternEmitter,
+        &GlobalTransform,
+        Has<OneShotPattern>,
+    )>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    library_handle: Res<PatternLibraryHandle>,
+    libraries: Res<Assets<PatternLibrary>>,
+    mut pool: ResMut<EntityPool<Bullet>>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+) {
+    let Some(library) = libraries.get(&library_handle.0) else {
+        return;
+    };
+    let target = q_player
+        .single()
+        .map_or(Vec3::ZERO, |transform| transform.translation());
+    let dt = time.delta_secs();
+
+    for (entity, mut emitter, global_transform, one_shot) in q_emitter.iter_mut() {
+        let Some(pattern) = library.patterns.get(&emitter.pattern) else {
+            continue;
+        };
+        let emitter = &mut *emitter;
+        emitter.fired.resize(pattern.timeline.len(), 0);
+        emitter.elapsed += dt;
+        emitter.rotation += pattern.rotation_speed.to_radians() * dt;
+
+        let (_, rotation, origin) = global_transform.to_scale_rotation_translation();
+        let facing = rotation.to_euler(EulerRot::XYZ).2 + emitter.rotation;
+        let aim = (target - origin).truncate().to_angle();
+
+        for (step, fired) in pattern.timeline.iter().zip(emitter.fired.iter_mut()) {
+            let due = repeats_due(step, emitter.elapsed);
+            let projectile = step.projectile.as_ref().unwrap_or(&pattern.projectile);
+            for repeat in *fired..due {
+                let base = if step.aimed { aim } else { facing };
+                let heading = base + (step.angle + step.turn * repeat as f32).to_radians();
+                let mut fire = |angle: f32| {
+                    spawn_enemy_bullet(
+                        &mut commands,
+                        &mut pool,
+                        &asset_server,
+                        origin,
+                        angle,
+                        projectile,
+                    );
+                };
+                match &step.shot {
+                    Shot::Ring { count } => {
+                        let spacing = std::f32::consts::TAU / (*count).max(1) as f32;
+                        for i in 0..*count {
+                            fire(heading + spacing * i as f32);
+                        }
+                    }
+                    Shot::Fan { count, spread } => {
+                        let spread = spread.to_radians();
+                        for i in 0..*count {
+                            let t = if *count > 1 {
+                                i as f32 / (*count - 1) as f32 - 0.5
+                            } else {
+                                0.0
+                            };
+                            fire(heading + spread * t);
+                        }
+                    }
+                    Shot::Pattern { pattern } => {
+                        spawn_nested_pattern(
+                            &mut commands,
+                            pattern,
+                            origin,
+                            heading,
+                            emitter.depth + 1,
+                        );
+                    }
+                }
+            }
+            *fired = due;
+        }
+
+        match pattern.repeat {
+            Some(repeat) if emitter.elapsed >= repeat => {
+                emitter.elapsed -= repeat.max(f32::EPSILON);
+                emitter.fired.iter_mut().for_each(|fired| *fired = 0);
+            }
+            None if one_shot
+                && pattern
+                    .timeline
+                    .iter()
+                    .zip(emitter.fired.iter())
+                    .all(|(step, fired)| *fired >= step.times) =>
+            {
+                commands.entity(entity).despawn();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Turns bullets with a burst into the burst's pattern once their time is up.
+pub fn burst_bullets(
+    mut commands: Commands,
+    q_bullet: Query<(Entity, &Bullet, &Transform)>,
+    mut pool: ResMut<EntityPool<Bullet>>,
+) {
+    for (entity, bullet, transform) in q_bullet.iter() {
+        let Some(script) = &bullet.script else {
+            continue;
+        };
+        let Some(burst) = &script.burst else {
+            continue;
+        };
+        if script.age < burst.after {
+            continue;
+        }
+        spawn_nested_pattern(
+            &mut commands,
+            &burst.pattern,
+            transform.translation,
+            bullet.direction.truncate().to_angle(),
+            1,
+        );
+        pool.release(&mut commands, entity);
+    }
+}
+
+pub struct BulletPatternPlugin;
+
+impl Plugin for BulletPatternPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<PatternLibrary>()
+            .init_asset_loader::<crate::asset_loader::PatternLibraryLoader>()
+            .add_systems(Startup, load_pattern_library)
+            .add_systems(
+                FixedUpdate,
+                (
+                    run_pattern_emitters,
+                    burst_bullets.after(crate::player::PlayerPlugin::move_bullet),
+                )
+                    .in_set(GameSet::Simulation),
+            );
+    }
+}
@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use rand::Rng;
+
+use crate::collision::EnemyHitPlayer;
+use crate::components_and_resources::{ConfigHandle, GameSpeed};
+use crate::config::{Config, current_settings};
+use crate::console::{self, AddConsoleCommand, ConsoleReply};
+use crate::damage::{Damaged, DeathEvent};
+use crate::rng::{GameRng, RngStream};
+use crate::schedule::GameSet;
+
+// screen shake, hit-stop and cursor lead for the game camera. Everything is scaled by the user
+// settings in config.toml, camera_effects = false turns all of it off.
+
+const MAX_SHAKE_OFFSET: f32 = 24.0;
+const MAX_SHAKE_ANGLE: f32 = 0.04;
+// trauma lost per second
+const TRAUMA_DECAY: f32 = 1.5;
+const LEAD_SMOOTHING: f32 = 6.0;
+
+const HIT_TRAUMA: f32 = 0.1;
+const KILL_TRAUMA: f32 = 0.25;
+const BIG_KILL_TRAUMA: f32 = 0.45;
+const PLAYER_HIT_TRAUMA: f32 = 0.7;
+// meteors at least this scale count as big kills and freeze the game for a moment
+const BIG_KILL_SCALE: f32 = 0.04;
+const HIT_STOP_SECONDS: f32 = 0.06;
+const HIT_STOP_SPEED: f32 = 0.05;
+
+/// Camera state driven by the effects. `lead` is the stable, cursor-following part of the
+/// camera offset; aiming adds it back so the crosshair stays under the pointer.
+#[derive(Component, Default)]
+pub struct CameraRig {
+    pub trauma: f32,
+    pub lead: Vec2,
+}
+
+#[derive(Event)]
+pub struct AddTrauma(pub f32);
+
+#[derive(Event)]
+pub struct HitStop {
+    pub seconds: f32,
+}
+
+// real-time seconds left on the current hit-stop
+#[derive(Resource, Default)]
+pub struct HitStopTimer(pub f32);
+
+pub fn react_to_hits(
+    mut damaged: EventReader<Damaged>,
+    mut deaths: EventReader<DeathEvent>,
+    mut enemy_hit_player: EventReader<EnemyHitPlayer>,
+    mut trauma: EventWriter<AddTrauma>,
+    mut hit_stop: EventWriter<HitStop>,
+) {
+    for hit in damaged.read() {
+        if hit.enemy {
+            trauma.write(AddTrauma(HIT_TRAUMA));
+        }
+    }
+    // the enemy is usually despawned by its death hooks by now
+    for death in deaths.read().filter(|death| death.enemy) {
+        if death.scale >= BIG_KILL_SCALE {
+            trauma.write(AddTrauma(BIG_KILL_TRAUMA));
+            hit_stop.write(HitStop {
+                seconds: HIT_STOP_SECONDS,
+            });
+        } else {
+            trauma.write(AddTrauma(KILL_TRAUMA));
+        }
+    }
+    for _ in enemy_hit_player.read() {
+        trauma.write(AddTrauma(PLAYER_HIT_TRAUMA));
+    }
+}
+
+pub fn apply_hit_stop(
+    mut events: EventReader<HitStop>,
+    mut timer: ResMut<HitStopTimer>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+    game_speed: Res<GameSpeed>,
+    config_handle: Res<ConfigHandle>,
+    configs: Res<Assets<Config>>,
+) {
+    let settings = current_settings(&config_handle, &configs);
+    for event in events.read() {
+        if settings.camera_effects && settings.hit_stop {
+            timer.0 = timer.0.max(event.seconds);
+        }
+    }
+
+    if timer.0 > 0.0 {
+        timer.0 -= real_time.delta_secs();
+    }
+    let speed = if timer.0 > 0.0 { HIT_STOP_SPEED } else { 1.0 };
+    virtual_time.set_relative_speed(speed * game_speed.0);
+}
+
+fn set_time_scale(In(args): In<Vec<String>>, mut game_speed: ResMut<GameSpeed>) -> ConsoleReply {
+    let scale: f32 = console::arg(&args, 0, "time scale")?;
+    if scale <= 0.0 || !scale.is_finite() {
+        return Err("the time scale has to be above 0".to_string());
+    }
+    game_speed.0 = scale;
+    Ok(format!("time scale {scale}"))
+}
+
+pub fn update_camera_rig(
+    mut q_camera: Query<(&mut CameraRig, &mut Transform), With<Camera2d>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut trauma_events: EventReader<AddTrauma>,
+    config_handle: Res<ConfigHandle>,
+    configs: Res<Assets<Config>>,
+    mut game_rng: ResMut<GameRng>,
+    time: Res<Time<Real>>,
+) {
+    let Ok((mut rig, mut transform)) = q_camera.single_mut() else {
+        return;
+    };
+    let settings = current_settings(&config_handle, &configs);
+    let dt = time.delta_secs();
+
+    for AddTrauma(amount) in trauma_events.read() {
+        rig.trauma = (rig.trauma + amount).min(1.0);
+    }
+    rig.trauma = (rig.trauma - TRAUMA_DECAY * dt).max(0.0);
+
+    if !settings.camera_effects {
+        rig.lead = Vec2::ZERO;
+        transform.translation = Vec3::ZERO.with_z(transform.translation.z);
+        transform.rotation = Quat::IDENTITY;
+        return;
+    }
+
+    // lead is computed from the pointer's screen position, not its world position, so moving
+    // the camera doesn't feed back into where it wants to go
+    let lead_target = q_window
+        .single()
+        .ok()
+        .and_then(|win| {
+            let cursor = win.cursor_position()?;
+            Some(Vec2::new(
+                cursor.x - win.size().x / 2.0,
+                win.size().y / 2.0 - cursor.y,
+            ))
+        })
+        .map_or(Vec2::ZERO, |cursor| cursor * settings.camera_lead);
+    let lead = rig.lead;
+    rig.lead = lead.lerp(lead_target, (LEAD_SMOOTHING * dt).min(1.0));
+
+    // shake grows with trauma squared so small hits stay subtle
+    let shake = rig.trauma * rig.trauma * settings.screen_shake;
+    let rng = game_rng.stream(RngStream::Camera);
+    let offset = Vec2::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0))
+        * MAX_SHAKE_OFFSET
+        * shake;
+    let angle = rng.random_range(-1.0..1.0) * MAX_SHAKE_ANGLE * shake;
+
+    transform.translation = (rig.lead + offset).extend(transform.translation.z);
+    transform.rotation = Quat::from_rotation_z(angle);
+}
+
+/// Camera lead to add to a cursor position measured from the window centre to get the world
+/// position under the pointer.
+pub fn camera_lead(q_rig: &Query<&CameraRig>) -> Vec3 {
+    q_rig
+        .single()
+        .map_or(Vec3::ZERO, |rig| rig.lead.extend(0.0))
+}
+
+pub struct CameraFxPlugin;
+
+impl Plugin for CameraFxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AddTrauma>()
+            .add_event::<HitStop>()
+            .init_resource::<HitStopTimer>()
+            .init_resource::<GameSpeed>()
+            .add_systems(
+                Update,
+                (react_to_hits, apply_hit_stop, update_camera_rig)
+                    .chain()
+                    .in_set(GameSet::Presentation),
+            )
+            .add_run_command("timescale", "timescale <speed>", set_time_scale);
+    }
+}
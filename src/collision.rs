@@ -0,0 +1,195 @@
+use bevy::ecs::entity::EntityHashSet;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::components_and_resources::{Bullet, Enemy, Player, Wall};
+use crate::schedule::GameSet;
+
+// every collider in the game belongs to exactly one layer. CollisionGroups decide which pairs
+// produce contacts/events at all, SolverGroups decide which of those pairs push each other.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Player,
+    PlayerProjectile,
+    Enemy,
+    EnemyProjectile,
+    Pickup,
+    Wall,
+}
+
+impl Layer {
+    pub const fn group(self) -> Group {
+        match self {
+            Layer::Player => Group::GROUP_1,
+            Layer::PlayerProjectile => Group::GROUP_2,
+            Layer::Enemy => Group::GROUP_3,
+            Layer::EnemyProjectile => Group::GROUP_4,
+            Layer::Pickup => Group::GROUP_5,
+            Layer::Wall => Group::GROUP_6,
+        }
+    }
+
+    /// Layers this layer detects contacts with.
+    pub const fn detects(self) -> Group {
+        match self {
+            Layer::Player => Group::GROUP_3.union(Group::GROUP_4).union(Group::GROUP_5),
+            Layer::PlayerProjectile => Group::GROUP_3.union(Group::GROUP_6),
+            Layer::Enemy => Group::GROUP_1
+                .union(Group::GROUP_2)
+                .union(Group::GROUP_3)
+                .union(Group::GROUP_6),
+            Layer::EnemyProjectile => Group::GROUP_1.union(Group::GROUP_6),
+            Layer::Pickup => Group::GROUP_1,
+            Layer::Wall => Group::GROUP_2.union(Group::GROUP_3).union(Group::GROUP_4),
+        }
+    }
+
+    /// Layers this layer exchanges contact forces with. Projectiles and pickups never push
+    /// anything, so a bullet can't shove a meteor around.
+    pub const fn pushes(self) -> Group {
+        match self {
+            Layer::Player => Group::GROUP_3,
+            Layer::Enemy => Group::GROUP_1.union(Group::GROUP_3).union(Group::GROUP_6),
+            Layer::Wall => Group::GROUP_3,
+            Layer::PlayerProjectile | Layer::EnemyProjectile | Layer::Pickup => Group::NONE,
+        }
+    }
+
+    pub const fn collision_groups(self) -> CollisionGroups {
+        CollisionGroups::new(self.group(), self.detects())
+    }
+
+    pub const fn solver_groups(self) -> SolverGroups {
+        SolverGroups::new(self.group(), self.pushes())
+    }
+
+    pub const fn groups(self) -> (CollisionGroups, SolverGroups) {
+        (self.collision_groups(), self.solver_groups())
+    }
+
+    /// `groups` with `other` left out, e.g. meteors flying in through the walls.
+    pub const fn groups_ignoring(self, other: Layer) -> (CollisionGroups, SolverGroups) {
+        (
+            CollisionGroups::new(self.group(), self.detects().difference(other.group())),
+            SolverGroups::new(self.group(), self.pushes().difference(other.group())),
+        )
+    }
+}
+
+// bullets are kinematic sensors: they report overlaps with meteors (dynamic), walls (fixed) and
+// the turret and bosses (kinematic) but never take part in the contact solver.
+pub fn projectile_physics(layer: Layer) -> impl Bundle {
+    (
+        layer.groups(),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        ActiveCollisionTypes::default()
+            | ActiveCollisionTypes::KINEMATIC_STATIC
+            | ActiveCollisionTypes::KINEMATIC_KINEMATIC,
+    )
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BulletHitEnemy {
+    pub bullet: Entity,
+    pub enemy: Entity,
+}
+
+#[allow(dead_code)]
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EnemyHitPlayer {
+    pub enemy: Entity,
+    pub player: Entity,
+}
+
+/// An enemy projectile reaching the player, the layers keep player bullets from ever doing so.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BulletHitPlayer {
+    pub bullet: Entity,
+    pub player: Entity,
+}
+
+#[allow(dead_code)]
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BulletHitWall {
+    pub bullet: Entity,
+    pub wall: Entity,
+}
+
+/// Reads the rapier collision stream once, works out what collided with what from the marker
+/// components and forwards typed events to the gameplay systems.
+///
+/// A bullet is handed out at most once per frame, so a bullet touching two meteors, or a meteor
+/// and a wall, in the same step only produces one hit. Enemies aren't used up: every bullet that
+/// reaches one in the same step hurts it, and damage already ignores the dead.
+#[allow(clippy::too_many_arguments)]
+pub fn route_collisions(
+    mut collision_events: EventReader<CollisionEvent>,
+    q_bullet: Query<(), With<Bullet>>,
+    q_enemy: Query<(), With<Enemy>>,
+    q_player: Query<(), With<Player>>,
+    q_wall: Query<(), With<Wall>>,
+    mut bullet_hit_enemy: EventWriter<BulletHitEnemy>,
+    mut enemy_hit_player: EventWriter<EnemyHitPlayer>,
+    mut bullet_hit_player: EventWriter<BulletHitPlayer>,
+    mut bullet_hit_wall: EventWriter<BulletHitWall>,
+) {
+    let _span = trace_span!("route_collisions", events = collision_events.len()).entered();
+    let mut consumed = EntityHashSet::default();
+
+    for event in collision_events.read() {
+        let CollisionEvent::Started(entity1, entity2, _) = *event else {
+            continue;
+        };
+
+        for (a, b) in [(entity1, entity2), (entity2, entity1)] {
+            if consumed.contains(&a) || consumed.contains(&b) {
+                break;
+            }
+            if q_bullet.contains(a) && q_enemy.contains(b) {
+                consumed.insert(a);
+                bullet_hit_enemy.write(BulletHitEnemy {
+                    bullet: a,
+                    enemy: b,
+                });
+                trace!(bullet = ?a, enemy = ?b, "bullet hit an enemy");
+                break;
+            }
+            if q_enemy.contains(a) && q_player.contains(b) {
+                enemy_hit_player.write(EnemyHitPlayer {
+                    enemy: a,
+                    player: b,
+                });
+                trace!(enemy = ?a, player = ?b, "enemy hit the player");
+                break;
+            }
+            if q_bullet.contains(a) && q_player.contains(b) {
+                consumed.insert(a);
+                bullet_hit_player.write(BulletHitPlayer {
+                    bullet: a,
+                    player: b,
+                });
+                trace!(bullet = ?a, player = ?b, "bullet hit the player");
+                break;
+            }
+            if q_bullet.contains(a) && q_wall.contains(b) {
+                consumed.insert(a);
+                bullet_hit_wall.write(BulletHitWall { bullet: a, wall: b });
+                break;
+            }
+        }
+    }
+}
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BulletHitEnemy>()
+            .add_event::<EnemyHitPlayer>()
+            .add_event::<BulletHitPlayer>()
+            .add_event::<BulletHitWall>()
+            .add_systems(FixedUpdate, route_collisions.in_set(GameSet::Collision));
+    }
+}
@@ -1,11 +1,25 @@
 use bevy::prelude::*;
-use std::time::Duration;
+
+use crate::bullet_pattern::BulletScript;
 
 use crate::config::Config;
 
 #[derive(Component)]
 pub struct Wall;
 
+// which edge of the window a Wall sits on, used to refit the bounds when the window resizes
+#[derive(Component, Clone, Copy)]
+pub enum WallSide {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Root of the game over overlay.
+#[derive(Component)]
+pub struct GameOverScreen;
+
 #[derive(Component)]
 pub struct Score {
     pub score: u32,
@@ -47,40 +61,16 @@ pub struct Accuracy {
     pub accuracy: f32,
 }
 
-#[derive(Component)]
-pub struct AnimationConfig {
-    pub first_sprite_index: usize,
-    pub last_sprite_index: usize,
-    pub fps: u8,
-    pub frame_timer: Timer,
-}
-
-impl AnimationConfig {
-    pub fn new(first: usize, last: usize, fps: u8, variant: String) -> Self {
-        Self {
-            first_sprite_index: first,
-            last_sprite_index: last,
-            fps,
-            frame_timer: Self::timer_from_fps(fps, variant),
-        }
-    }
-
-    pub fn timer_from_fps(fps: u8, variant: String) -> Timer {
-        if variant == "once" {
-            Timer::new(Duration::from_secs_f32(1.0 / (fps as f32)), TimerMode::Once)
-        } else {
-            Timer::new(
-                Duration::from_secs_f32(1.0 / (fps as f32)),
-                TimerMode::Repeating,
-            )
-        }
-    }
-}
-
 #[derive(Component)]
 pub struct Bullet {
     pub speed: f32,
     pub direction: Vec3,
+    // a bullet that hits nothing is retired when either runs out
+    pub lifetime: Timer,
+    pub range: f32,
+    pub travelled: f32,
+    // speed curve, turning and bursting for bullets fired by a pattern, None flies straight
+    pub script: Option<BulletScript>,
 }
 
 #[derive(Component)]
@@ -88,25 +78,37 @@ pub struct BulletFireSound {
     pub duration: Timer,
 }
 
-#[allow(dead_code)]
-#[derive(Resource)]
-pub struct BulletFadeTimer(pub Timer);
-
-#[allow(dead_code)]
-#[derive(Resource)]
-pub struct PlayerFireAnimationTimer(pub Timer);
-
+// direction, speed and enemy_rotation are spawn parameters, the meteor is moved by its rapier
+// Velocity afterwards
 #[allow(dead_code)]
 #[derive(Component)]
 pub struct Enemy {
-    pub health: f32,
     pub direction: Vec3,
     pub speed: f32,
     pub enemy_rotation: f32,
 }
 
+/// The looping background music track.
+#[derive(Component)]
+pub struct Music;
+
+/// How fast game time runs when nothing slows it down, the replay viewer speeds it up and
+/// down. Hit-stop scales this rather than overriding it.
+#[derive(Resource)]
+pub struct GameSpeed(pub f32);
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
 #[derive(Resource)]
 pub struct EnemySapwnTimer(pub Timer);
 
+/// Current level, picks the level's background art.
+#[derive(Resource, Default)]
+pub struct Level(pub u32);
+
 #[derive(Resource)]
 pub struct ConfigHandle(pub Handle<Config>);
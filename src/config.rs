@@ -3,19 +3,30 @@ use bevy::reflect::TypePath;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::components_and_resources::ConfigHandle;
+use crate::spawning::SpawnStrategy;
+
 #[derive(Serialize, Deserialize, Debug, Asset, TypePath)]
 pub struct Config {
     pub assets: Assets,
     pub settings: Settings,
+    #[serde(default)]
+    pub spawning: Spawning,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Assets {
     pub background: PathBuf,
+    /// Backdrop per level, cycled when there are more levels than entries. `background` is
+    /// used for every level when empty.
+    #[serde(default)]
+    pub backgrounds: Vec<PathBuf>,
     pub turret: PathBuf,
     pub turret_base: PathBuf,
     pub crosshair: PathBuf,
     pub bgmusic: PathBuf,
+    /// Played during boss fights, `bgmusic` sped up when unset.
+    pub boss_music: Option<PathBuf>,
     pub bullet: PathBuf,
     pub fire_sound_fx: PathBuf,
     pub meteor: PathBuf,
@@ -23,8 +34,88 @@ pub struct Assets {
     pub collision_sound: PathBuf,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Settings {}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Master switch for screen shake, hit-stop and camera lead (accessibility).
+    pub camera_effects: bool,
+    /// Screen shake strength, 0.0 to 1.0.
+    pub screen_shake: f32,
+    /// Briefly slow the game down on big kills.
+    pub hit_stop: bool,
+    /// How far the camera drifts towards the cursor, as a fraction of the cursor's distance
+    /// from the screen centre. 0.0 keeps the camera still.
+    pub camera_lead: f32,
+    /// `low` turns off HDR, bloom and light glows for weaker (mobile) GPUs.
+    pub quality: GraphicsQuality,
+    /// Plays the run with this seed, the same seed gives the same meteors.
+    pub seed: Option<u64>,
+    /// Plays today's daily challenge seed when `seed` is unset.
+    pub daily: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphicsQuality {
+    Low,
+    #[default]
+    High,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            camera_effects: true,
+            screen_shake: 1.0,
+            hit_stop: true,
+            camera_lead: 0.0,
+            quality: GraphicsQuality::High,
+            seed: None,
+            daily: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Spawning {
+    /// Meteors never appear closer than this to the turret or the station.
+    pub safe_radius: f32,
+    /// Seconds a meteor's warning marker shows before it arrives.
+    pub warning_seconds: f32,
+    /// Strategy for each wave, cycled when there are more waves than entries.
+    pub strategies: Vec<SpawnStrategy>,
+}
+
+impl Default for Spawning {
+    fn default() -> Self {
+        Self {
+            safe_radius: 250.0,
+            warning_seconds: 0.8,
+            strategies: vec![SpawnStrategy::Edges, SpawnStrategy::Flank],
+        }
+    }
+}
+
+impl Spawning {
+    pub fn strategy(&self, wave: u32) -> SpawnStrategy {
+        match self.strategies.len() {
+            0 => SpawnStrategy::Edges,
+            len => self.strategies[(wave.saturating_sub(1) as usize) % len],
+        }
+    }
+}
+
+/// The loaded settings, or the defaults while config.toml is still loading.
+pub fn current_settings(
+    config_handle: &ConfigHandle,
+    configs: &bevy::asset::Assets<Config>,
+) -> Settings {
+    configs
+        .get(&config_handle.0)
+        .map(|config| config.settings.clone())
+        .unwrap_or_default()
+}
 
 #[allow(dead_code)]
 impl Config {
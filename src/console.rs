@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use std::str::FromStr;
+
+#[cfg(feature = "dev-tools")]
+pub mod panel;
+#[cfg(feature = "dev-tools")]
+pub use panel::ConsolePlugin;
+
+// the developer console drops down from the top of the screen with the backquote key. Each
+// plugin registers its own commands with `add_console_command`, a command is a one-shot system
+// that gets the words after its name and answers with a line for the console. Commands that
+// change how the run plays out are added with `add_run_command` and refused while a run is
+// recorded or a replay watched, the recording only holds the player's input. Without the
+// dev-tools feature there is no console, so registering a command does nothing.
+
+/// What a console command prints back, errors show up in red.
+pub type ConsoleReply = Result<String, String>;
+
+pub trait AddConsoleCommand {
+    /// Registers `system` as the console command `name`. `usage` is shown by `help` and when
+    /// the command is given bad arguments.
+    // only the dev-tools setup registers plain commands, without the feature both methods do
+    // nothing anyway
+    #[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+    fn add_console_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, ConsoleReply, M> + 'static,
+    ) -> &mut Self;
+
+    /// `add_console_command` for commands that change the simulation, like spawning or god
+    /// mode. They don't run while recording or watching a replay.
+    fn add_run_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, ConsoleReply, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl AddConsoleCommand for App {
+    fn add_console_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, ConsoleReply, M> + 'static,
+    ) -> &mut Self {
+        #[cfg(feature = "dev-tools")]
+        panel::register(self, name, usage, false, system);
+        #[cfg(not(feature = "dev-tools"))]
+        let _ = (name, usage, system);
+        self
+    }
+
+    fn add_run_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, ConsoleReply, M> + 'static,
+    ) -> &mut Self {
+        #[cfg(feature = "dev-tools")]
+        panel::register(self, name, usage, true, system);
+        #[cfg(not(feature = "dev-tools"))]
+        let _ = (name, usage, system);
+        self
+    }
+}
+
+/// Parses argument `index`, `what` names it in the error.
+pub fn arg<T: FromStr>(args: &[String], index: usize, what: &str) -> Result<T, String> {
+    optional_arg(args, index, what)?.ok_or_else(|| format!("missing {what}"))
+}
+
+pub fn optional_arg<T: FromStr>(
+    args: &[String],
+    index: usize,
+    what: &str,
+) -> Result<Option<T>, String> {
+    args.get(index)
+        .map(|word| {
+            word.parse()
+                .map_err(|_| format!("{word} isn't a valid {what}"))
+        })
+        .transpose()
+}
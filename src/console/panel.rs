@@ -0,0 +1,303 @@
+use bevy::ecs::system::SystemId;
+use bevy::input::InputSystem;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+use super::{AddConsoleCommand, ConsoleReply};
+use crate::replay::ReplayMode;
+use crate::schedule::GameSet;
+
+const HISTORY_LINES: usize = 14;
+const ENTITY_DUMP_LINES: usize = 40;
+
+struct ConsoleCommand {
+    usage: &'static str,
+    changes_run: bool,
+    system: SystemId<In<Vec<String>>, ConsoleReply>,
+}
+
+/// Every command the console knows, by name.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(BTreeMap<&'static str, ConsoleCommand>);
+
+pub(super) fn register<M>(
+    app: &mut App,
+    name: &'static str,
+    usage: &'static str,
+    changes_run: bool,
+    system: impl IntoSystem<In<Vec<String>>, ConsoleReply, M> + 'static,
+) {
+    let system = app.world_mut().register_system(system);
+    app.world_mut()
+        .get_resource_or_init::<ConsoleCommands>()
+        .0
+        .insert(
+            name,
+            ConsoleCommand {
+                usage,
+                changes_run,
+                system,
+            },
+        );
+}
+
+/// Runs one console line against the world.
+pub fn execute(world: &mut World, line: &str) -> ConsoleReply {
+    let mut words = line.split_whitespace().map(str::to_string);
+    let Some(name) = words.next() else {
+        return Ok(String::new());
+    };
+    let commands = world.get_resource::<ConsoleCommands>();
+    let Some((system, usage, changes_run)) = commands
+        .and_then(|commands| commands.0.get(name.as_str()))
+        .map(|command| (command.system, command.usage, command.changes_run))
+    else {
+        return Err(format!("unknown command {name}, try help"));
+    };
+    // the recording only holds the player's input, anything else would play out differently
+    if changes_run
+        && world
+            .get_resource::<ReplayMode>()
+            .is_some_and(|mode| !matches!(mode, ReplayMode::Live))
+    {
+        return Err(format!(
+            "{name} changes the run, it can't be used while recording or watching a replay"
+        ));
+    }
+    world
+        .run_system_with(system, words.collect())
+        .map_err(|err| format!("{name} failed: {err}"))?
+        .map_err(|err| format!("{err}, usage: {usage}"))
+}
+
+#[derive(Resource, Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    history: Vec<(String, bool)>,
+    submitted: Vec<String>,
+    recall: Option<usize>,
+    pending: Option<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>, error: bool) {
+        self.history.push((line.into(), error));
+    }
+}
+
+#[derive(Component)]
+pub struct ConsolePanel;
+
+#[derive(Component)]
+pub struct ConsoleText;
+
+/// Types into the console while it's open. The keyboard is cleared afterwards, so nothing
+/// typed reaches the game, not even the Escape that closes it.
+pub fn read_console_keys(
+    mut console: ResMut<Console>,
+    mut keys: EventReader<KeyboardInput>,
+    mut keyboard_input: ResMut<ButtonInput<KeyCode>>,
+) {
+    let was_open = console.open;
+    for key in keys.read() {
+        if !key.state.is_pressed() {
+            continue;
+        }
+        if key.key_code == KeyCode::Backquote {
+            console.open = !console.open;
+            continue;
+        }
+        if !console.open {
+            continue;
+        }
+        match &key.logical_key {
+            Key::Character(text) => console.input.push_str(text),
+            Key::Space => console.input.push(' '),
+            Key::Escape => console.open = false,
+            Key::Backspace => {
+                console.input.pop();
+            }
+            Key::Enter => {
+                let line = std::mem::take(&mut console.input);
+                console.recall = None;
+                if !line.trim().is_empty() {
+                    console.submitted.push(line.clone());
+                    console.pending = Some(line);
+                }
+            }
+            Key::ArrowUp | Key::ArrowDown if !console.submitted.is_empty() => {
+                let last = console.submitted.len() - 1;
+                let recall = match (console.recall, &key.logical_key) {
+                    (None, Key::ArrowUp) => last,
+                    (Some(index), Key::ArrowUp) => index.saturating_sub(1),
+                    (Some(index), _) => (index + 1).min(last),
+                    (None, _) => continue,
+                };
+                console.recall = Some(recall);
+                console.input = console.submitted[recall].clone();
+            }
+            _ => {}
+        }
+    }
+    if was_open || console.open {
+        keyboard_input.reset_all();
+    }
+}
+
+pub fn run_console_commands(world: &mut World) {
+    // checked before borrowing mutably, so draw_console only redraws when something changed
+    if world.resource::<Console>().pending.is_none() {
+        return;
+    }
+    let Some(line) = world.resource_mut::<Console>().pending.take() else {
+        return;
+    };
+    let reply = execute(world, &line);
+    let mut console = world.resource_mut::<Console>();
+    console.print(format!("> {line}"), false);
+    match reply {
+        Ok(text) => text.lines().for_each(|line| console.print(line, false)),
+        Err(text) => console.print(text, true),
+    }
+}
+
+pub fn setup_console(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::FlexEnd,
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.02, 0.02, 0.06, 0.9)),
+            GlobalZIndex(30),
+            Visibility::Hidden,
+            ConsolePanel,
+        ))
+        .with_child((
+            Text::default(),
+            TextFont {
+                font_size: 18.0,
+                ..default()
+            },
+            ConsoleText,
+        ));
+}
+
+pub fn draw_console(
+    console: Res<Console>,
+    mut commands: Commands,
+    mut q_panel: Query<&mut Visibility, With<ConsolePanel>>,
+    q_text: Query<Entity, With<ConsoleText>>,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    for mut visibility in q_panel.iter_mut() {
+        *visibility = if console.open {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+    let Ok(text) = q_text.single() else {
+        return;
+    };
+    // one span per line so errors can be coloured on their own
+    let start = console.history.len().saturating_sub(HISTORY_LINES);
+    let lines = console.history[start..]
+        .iter()
+        .map(|(line, error)| (format!("{line}\n"), *error))
+        .chain([(format!("> {}_", console.input), false)]);
+    commands
+        .entity(text)
+        .despawn_related::<Children>()
+        .with_children(|parent| {
+            for (line, error) in lines {
+                let color = if error {
+                    Color::srgb(1.0, 0.4, 0.4)
+                } else {
+                    Color::WHITE
+                };
+                parent.spawn((
+                    TextSpan::new(line),
+                    TextFont {
+                        font_size: 18.0,
+                        ..default()
+                    },
+                    TextColor(color),
+                ));
+            }
+        });
+}
+
+fn help(In(_): In<Vec<String>>, commands: Res<ConsoleCommands>) -> ConsoleReply {
+    Ok(commands
+        .0
+        .values()
+        .map(|command| command.usage)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// Lists live entities with their names or components, the whole list goes to the log.
+fn dump_entities(In(_): In<Vec<String>>, world: &mut World) -> ConsoleReply {
+    let mut q_entities = world.query::<(Entity, Option<&Name>)>();
+    let world: &World = world;
+    let mut lines: Vec<String> = q_entities
+        .iter(world)
+        .map(|(entity, name)| {
+            let description = match name {
+                Some(name) => name.to_string(),
+                None => world
+                    .inspect_entity(entity)
+                    .map(|components| {
+                        components
+                            .map(|info| short_name(info.name()))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    })
+                    .unwrap_or_default(),
+            };
+            format!("{entity}: {description}")
+        })
+        .collect();
+    for line in &lines {
+        info!("{line}");
+    }
+    let total = lines.len();
+    lines.truncate(ENTITY_DUMP_LINES);
+    lines.push(format!("{total} entities, full list in the log"));
+    Ok(lines.join("\n"))
+}
+
+// bevy_sprite::sprite::Sprite -> Sprite
+fn short_name(name: &str) -> &str {
+    let path = name.split('<').next().unwrap_or(name);
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .add_console_command("help", "help", help)
+            .add_console_command("entities", "entities", dump_entities)
+            .add_systems(Startup, setup_console)
+            .add_systems(PreUpdate, read_console_keys.after(InputSystem))
+            .add_systems(
+                Update,
+                (run_console_commands, draw_console)
+                    .chain()
+                    .in_set(GameSet::Presentation),
+            );
+    }
+}
@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::components_and_resources::{Enemy, HitSoundBulletMeteor, Score, Smoke};
+use crate::lighting::{self, Light2d};
+use crate::pool::EntityPool;
+use crate::schedule::GameSet;
+use crate::{particles, utility};
+
+// everything that can be hurt carries a Health, everything that hurts carries a DamageDealer, and
+// all damage goes through DamageEvent. Dying sends a DeathEvent and runs the entity's OnDeath
+// hooks, so new content describes what happens on death instead of writing its own despawn
+// logic. The hooks can despawn the entity before presentation reads the events, so the events
+// carry what the feedback needs to know about it.
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        (self.current / self.max.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageKind {
+    /// Bullets and other projectiles.
+    Kinetic,
+    /// Bodies running into each other.
+    Impact,
+}
+
+/// Damage the entity deals to whatever it hits.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DamageDealer {
+    pub amount: f32,
+    pub kind: DamageKind,
+}
+
+/// Fraction of each kind of damage the entity ignores, 0.0 takes it all and 1.0 is immune.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Resistances {
+    pub kinetic: f32,
+    pub impact: f32,
+}
+
+impl Resistances {
+    pub fn multiplier(&self, kind: DamageKind) -> f32 {
+        let resistance = match kind {
+            DamageKind::Kinetic => self.kinetic,
+            DamageKind::Impact => self.impact,
+        };
+        (1.0 - resistance).clamp(0.0, 1.0)
+    }
+}
+
+/// Ignores all damage for `seconds` after being hurt.
+#[derive(Component, Debug)]
+pub struct Invulnerability {
+    pub seconds: f32,
+    remaining: f32,
+}
+
+impl Invulnerability {
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            seconds,
+            remaining: 0.0,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.remaining > 0.0
+    }
+}
+
+/// Takes no damage at all, the console's god mode.
+#[derive(Component, Debug)]
+pub struct Invincible;
+
+/// Passes damage taken by this entity on to `target`, scaled by `multiplier`. Used for weak
+/// points and other parts that share their owner's health.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DamageRelay {
+    pub target: Entity,
+    pub multiplier: f32,
+}
+
+/// What happens when the entity dies, run in order.
+#[derive(Debug, Clone)]
+pub enum DeathHook {
+    Score(u32),
+    /// Particle preset from effects.fx.toml.
+    Effect(String),
+    /// Short-lived sprite, like the smoke puff meteors leave behind.
+    Sprite {
+        path: PathBuf,
+        scale: f32,
+    },
+    Sound {
+        path: PathBuf,
+        volume: f32,
+    },
+    Flash {
+        light: Light2d,
+        seconds: f32,
+    },
+    Despawn,
+}
+
+#[derive(Component, Debug, Clone, Default)]
+pub struct OnDeath(pub Vec<DeathHook>);
+
+/// Request to hurt `target`. `source` is the dealer, if there is one.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub kind: DamageKind,
+    pub source: Option<Entity>,
+}
+
+impl DamageEvent {
+    pub fn from_dealer(dealer: &DamageDealer, source: Entity, target: Entity) -> Self {
+        Self {
+            target,
+            amount: dealer.amount,
+            kind: dealer.kind,
+            source: Some(source),
+        }
+    }
+}
+
+/// Sent after damage has been taken off an entity's health, drives the hit feedback.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Damaged {
+    pub entity: Entity,
+    pub amount: f32,
+    pub position: Vec3,
+    pub enemy: bool,
+}
+
+/// Sent once when an entity's health reaches zero.
+#[allow(dead_code)]
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub position: Vec3,
+    /// The entity's own scale, meteors come in sizes.
+    pub scale: f32,
+    pub enemy: bool,
+    pub killer: Option<Entity>,
+}
+
+pub fn tick_invulnerability(mut q_invulnerable: Query<&mut Invulnerability>, time: Res<Time>) {
+    for mut invulnerability in q_invulnerable.iter_mut() {
+        invulnerability.remaining = (invulnerability.remaining - time.delta_secs()).max(0.0);
+    }
+}
+
+/// Applies pending damage after relays, resistances and invulnerability.
+#[allow(clippy::type_complexity)]
+pub fn apply_damage(
+    mut events: EventReader<DamageEvent>,
+    mut q_health: Query<(
+        &mut Health,
+        Option<&Resistances>,
+        Option<&mut Invulnerability>,
+        Option<&Transform>,
+        Has<Enemy>,
+    )>,
+    q_relay: Query<&DamageRelay>,
+    q_invincible: Query<(), With<Invincible>>,
+    q_transform: Query<&GlobalTransform>,
+    mut damaged: EventWriter<Damaged>,
+    mut deaths: EventWriter<DeathEvent>,
+) {
+    for event in events.read() {
+        let (target, relayed_amount) = match q_relay.get(event.target) {
+            Ok(relay) => (relay.target, event.amount * relay.multiplier),
+            Err(_) => (event.target, event.amount),
+        };
+        let Ok((mut health, resistances, invulnerability, local, enemy)) = q_health.get_mut(target)
+        else {
+            continue;
+        };
+        if health.is_dead() || q_invincible.contains(target) {
+            continue;
+        }
+        if invulnerability.as_ref().is_some_and(|i| i.is_active()) {
+            continue;
+        }
+        let multiplier = resistances.map_or(1.0, |r| r.multiplier(event.kind));
+        let amount = (relayed_amount * multiplier).min(health.current);
+        if amount <= 0.0 {
+            continue;
+        }
+
+        health.current -= amount;
+        if let Some(mut invulnerability) = invulnerability {
+            invulnerability.remaining = invulnerability.seconds;
+        }
+        // numbers and effects show up where the hit landed, which is the weak point for relays
+        let position = q_transform
+            .get(event.target)
+            .map_or(Vec3::ZERO, |transform| transform.translation());
+        damaged.write(Damaged {
+            entity: target,
+            amount,
+            position,
+            enemy,
+        });
+        if health.is_dead() {
+            deaths.write(DeathEvent {
+                entity: target,
+                position: q_transform
+                    .get(target)
+                    .map_or(position, |transform| transform.translation()),
+                scale: local.map_or(1.0, |transform| transform.scale.x),
+                enemy,
+                killer: event.source,
+            });
+        }
+    }
+}
+
+pub fn run_death_hooks(
+    mut commands: Commands,
+    mut deaths: EventReader<DeathEvent>,
+    q_on_death: Query<&OnDeath>,
+    mut q_score: Query<&mut Score>,
+    mut smoke_pool: ResMut<EntityPool<Smoke>>,
+    mut sound_pool: ResMut<EntityPool<HitSoundBulletMeteor>>,
+    asset_server: Res<AssetServer>,
+) {
+    for death in deaths.read() {
+        let Ok(on_death) = q_on_death.get(death.entity) else {
+            continue;
+        };
+        for hook in on_death.0.iter() {
+            match hook {
+                DeathHook::Score(points) => {
+                    if let Ok(mut score) = q_score.single_mut() {
+                        score.score += points;
+                    }
+                }
+                DeathHook::Effect(preset) => {
+                    particles::spawn_effect(
+                        &mut commands,
+                        preset,
+                        Transform::from_translation(death.position),
+                    );
+                }
+                DeathHook::Sprite { path, scale } => {
+                    utility::spawn_sprite(
+                        &mut commands,
+                        &mut smoke_pool,
+                        asset_server.clone(),
+                        path.clone(),
+                        Transform::from_translation(death.position).with_scale(Vec3::splat(*scale)),
+                    );
+                }
+                DeathHook::Sound { path, volume } => {
+                    utility::spawn_audio(
+                        &mut commands,
+                        &mut sound_pool,
+                        asset_server.clone(),
+                        path.clone(),
+                        *volume,
+                        2.0,
+                    );
+                }
+                DeathHook::Flash { light, seconds } => {
+                    lighting::spawn_flash(&mut commands, death.position, *light, *seconds);
+                }
+                DeathHook::Despawn => {
+                    commands.entity(death.entity).despawn();
+                }
+            }
+        }
+    }
+}
+
+pub struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<Damaged>()
+            .add_event::<DeathEvent>()
+            .add_systems(
+                FixedUpdate,
+                (tick_invulnerability, apply_damage, run_death_hooks)
+                    .chain()
+                    .after(crate::collision::route_collisions)
+                    .in_set(GameSet::Collision),
+            );
+    }
+}
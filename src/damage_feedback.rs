@@ -0,0 +1,305 @@
+use bevy::prelude::*;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef};
+use bevy::sprite::{AlphaMode2d, Material2d, Material2dPlugin};
+
+use crate::boss::Boss;
+use crate::components_and_resources::Enemy;
+use crate::damage::{Damaged, Health};
+use crate::schedule::GameSet;
+
+// what the player sees when something takes damage: the sprite flashes white, a number floats
+// up from the hit, and enemies get a small health bar until they die.
+//
+// A sprite tint can only darken the texture, so the flash is an overlay in the sprite's shape
+// drawn with HitFlashMaterial, fading from the flash colour back to the sprite underneath.
+
+// white, and far enough above 1.0 to bloom with HDR on
+const FLASH_COLOR: LinearRgba = LinearRgba::rgb(6.0, 6.0, 6.0);
+const FLASH_SECONDS: f32 = 0.1;
+// just above the sprite, in its own (scaled) space
+const FLASH_Z: f32 = 0.5;
+
+const NUMBER_SECONDS: f32 = 0.7;
+const NUMBER_RISE_SPEED: f32 = 60.0;
+const NUMBER_FONT_SIZE: f32 = 22.0;
+const NUMBER_Z: f32 = 10.0;
+
+const BAR_SIZE: Vec2 = Vec2::new(48.0, 5.0);
+// distance above the enemy's centre
+const BAR_OFFSET: f32 = 40.0;
+const BAR_Z: f32 = 9.0;
+
+/// White flash on a damaged sprite, `overlay` is the child it is drawn with.
+#[derive(Component)]
+pub struct HitFlash {
+    timer: Timer,
+    overlay: Entity,
+}
+
+/// Covers the opaque pixels of `texture` within `uv_rect` with `color` at `amount` opacity.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct HitFlashMaterial {
+    #[uniform(0)]
+    pub color: LinearRgba,
+    /// Min uv and size of the sprite's frame, negative sizes flip it.
+    #[uniform(0)]
+    pub uv_rect: Vec4,
+    #[uniform(0)]
+    pub amount: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Handle<Image>,
+}
+
+impl Material2d for HitFlashMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/hit_flash.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+}
+
+// the unit quad flash overlays are drawn on, scaled to the sprite
+#[derive(Resource)]
+pub struct FlashQuad(pub Handle<Mesh>);
+
+#[derive(Component)]
+pub struct DamageNumber {
+    timer: Timer,
+}
+
+/// World-space health bar that follows `target`, its fill is a child.
+#[derive(Component)]
+pub struct HealthBar {
+    pub target: Entity,
+}
+
+#[derive(Component)]
+pub struct HealthBarFill;
+
+pub fn setup_hit_flashes(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(FlashQuad(meshes.add(Rectangle::new(1.0, 1.0))));
+}
+
+/// Size the sprite is drawn at and the uv rect of the part of its image it shows, None while
+/// the image or atlas is still loading.
+fn sprite_frame(
+    sprite: &Sprite,
+    images: &Assets<Image>,
+    layouts: &Assets<TextureAtlasLayout>,
+) -> Option<(Vec2, Vec4)> {
+    let image_size = images.get(&sprite.image)?.size_f32();
+    let rect = match &sprite.texture_atlas {
+        Some(atlas) => layouts
+            .get(&atlas.layout)?
+            .textures
+            .get(atlas.index)?
+            .as_rect(),
+        None => sprite
+            .rect
+            .unwrap_or(Rect::from_corners(Vec2::ZERO, image_size)),
+    };
+    let mut uv_min = rect.min / image_size;
+    let mut uv_size = rect.size() / image_size;
+    if sprite.flip_x {
+        uv_min.x += uv_size.x;
+        uv_size.x = -uv_size.x;
+    }
+    if sprite.flip_y {
+        uv_min.y += uv_size.y;
+        uv_size.y = -uv_size.y;
+    }
+    let size = sprite.custom_size.unwrap_or(rect.size());
+    Some((size, Vec4::new(uv_min.x, uv_min.y, uv_size.x, uv_size.y)))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_hit_flashes(
+    mut commands: Commands,
+    mut damaged: EventReader<Damaged>,
+    mut q_sprite: Query<(&Sprite, Option<&mut HitFlash>)>,
+    images: Res<Assets<Image>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    quad: Res<FlashQuad>,
+    mut materials: ResMut<Assets<HitFlashMaterial>>,
+) {
+    for hit in damaged.read() {
+        let Ok((sprite, flash)) = q_sprite.get_mut(hit.entity) else {
+            continue;
+        };
+        if let Some(mut flash) = flash {
+            flash.timer.reset();
+            continue;
+        }
+        let Some((size, uv_rect)) = sprite_frame(sprite, &images, &layouts) else {
+            continue;
+        };
+        let material = materials.add(HitFlashMaterial {
+            color: FLASH_COLOR,
+            uv_rect,
+            amount: 1.0,
+            texture: sprite.image.clone(),
+        });
+        let overlay = commands
+            .spawn((
+                Mesh2d(quad.0.clone()),
+                MeshMaterial2d(material),
+                Transform::from_xyz(0.0, 0.0, FLASH_Z).with_scale(size.extend(1.0)),
+                ChildOf(hit.entity),
+            ))
+            .id();
+        commands.entity(hit.entity).insert(HitFlash {
+            timer: Timer::from_seconds(FLASH_SECONDS, TimerMode::Once),
+            overlay,
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_hit_flashes(
+    mut commands: Commands,
+    mut q_flash: Query<(Entity, &mut HitFlash, &Sprite)>,
+    mut q_overlay: Query<(&MeshMaterial2d<HitFlashMaterial>, &mut Transform)>,
+    mut materials: ResMut<Assets<HitFlashMaterial>>,
+    images: Res<Assets<Image>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, sprite) in q_flash.iter_mut() {
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            commands.entity(flash.overlay).despawn();
+            commands.entity(entity).remove::<HitFlash>();
+            continue;
+        }
+        let Ok((handle, mut transform)) = q_overlay.get_mut(flash.overlay) else {
+            continue;
+        };
+        let Some(material) = materials.get_mut(&handle.0) else {
+            continue;
+        };
+        material.amount = 1.0 - flash.timer.fraction();
+        // animated sprites keep flashing in the shape of their current frame
+        if let Some((size, uv_rect)) = sprite_frame(sprite, &images, &layouts) {
+            material.uv_rect = uv_rect;
+            material.texture = sprite.image.clone();
+            transform.scale = size.extend(1.0);
+        }
+    }
+}
+
+pub fn spawn_damage_numbers(mut commands: Commands, mut damaged: EventReader<Damaged>) {
+    for hit in damaged.read() {
+        commands.spawn((
+            Text2d::new(format!("{}", hit.amount.round() as i32)),
+            TextFont {
+                font_size: NUMBER_FONT_SIZE,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.9, 0.4)),
+            Transform::from_translation(hit.position.truncate().extend(NUMBER_Z)),
+            DamageNumber {
+                timer: Timer::from_seconds(NUMBER_SECONDS, TimerMode::Once),
+            },
+        ));
+    }
+}
+
+pub fn update_damage_numbers(
+    mut commands: Commands,
+    mut q_number: Query<(Entity, &mut DamageNumber, &mut Transform, &mut TextColor)>,
+    time: Res<Time>,
+) {
+    for (entity, mut number, mut transform, mut color) in q_number.iter_mut() {
+        number.timer.tick(time.delta());
+        if number.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation.y += NUMBER_RISE_SPEED * time.delta_secs();
+        color.0.set_alpha(1.0 - number.timer.fraction());
+    }
+}
+
+/// Gives an enemy a health bar (bosses have their own) the first time it takes damage and survives.
+pub fn spawn_health_bars(
+    mut commands: Commands,
+    mut damaged: EventReader<Damaged>,
+    q_enemy: Query<&Health, (With<Enemy>, Without<Boss>)>,
+    q_bar: Query<&HealthBar>,
+) {
+    let mut with_bar: Vec<Entity> = q_bar.iter().map(|bar| bar.target).collect();
+    for hit in damaged.read() {
+        let Ok(health) = q_enemy.get(hit.entity) else {
+            continue;
+        };
+        if health.is_dead() || with_bar.contains(&hit.entity) {
+            continue;
+        }
+        with_bar.push(hit.entity);
+        commands
+            .spawn((
+                Sprite::from_color(Color::srgba(0.0, 0.0, 0.0, 0.6), BAR_SIZE),
+                Transform::from_translation(hit.position.truncate().extend(BAR_Z)),
+                HealthBar { target: hit.entity },
+            ))
+            .with_child((
+                Sprite {
+                    color: Color::srgb(0.3, 1.0, 0.4),
+                    custom_size: Some(BAR_SIZE),
+                    anchor: bevy::sprite::Anchor::CenterLeft,
+                    ..default()
+                },
+                Transform::from_xyz(-BAR_SIZE.x / 2.0, 0.0, 0.1),
+                HealthBarFill,
+            ));
+    }
+}
+
+/// Keeps bars above their enemy and sized to its health, removes bars whose enemy is gone.
+pub fn update_health_bars(
+    mut commands: Commands,
+    mut q_bar: Query<(Entity, &HealthBar, &mut Transform, &Children)>,
+    q_target: Query<(&Health, &GlobalTransform)>,
+    mut q_fill: Query<&mut Sprite, With<HealthBarFill>>,
+) {
+    for (entity, bar, mut transform, children) in q_bar.iter_mut() {
+        let Ok((health, target_transform)) = q_target.get(bar.target) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        if health.is_dead() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let position = target_transform.translation().truncate() + Vec2::Y * BAR_OFFSET;
+        transform.translation = position.extend(BAR_Z);
+        let fraction = health.fraction();
+        for child in children.iter() {
+            if let Ok(mut sprite) = q_fill.get_mut(child) {
+                sprite.custom_size = Some(Vec2::new(BAR_SIZE.x * fraction, BAR_SIZE.y));
+                sprite.color = Color::srgb(1.0 - fraction, 0.3 + 0.7 * fraction, 0.3);
+            }
+        }
+    }
+}
+
+pub struct DamageFeedbackPlugin;
+
+impl Plugin for DamageFeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<HitFlashMaterial>::default())
+            .add_systems(Startup, setup_hit_flashes)
+            .add_systems(
+                Update,
+                (
+                    (update_hit_flashes, start_hit_flashes).chain(),
+                    (spawn_damage_numbers, update_damage_numbers),
+                    (spawn_health_bars, update_health_bars),
+                )
+                    .in_set(GameSet::Presentation),
+            );
+    }
+}
@@ -0,0 +1,118 @@
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::{Collider, ColliderDisabled};
+
+use crate::boss::Boss;
+use crate::components_and_resources::{Bullet, Enemy, EnemySapwnTimer};
+use crate::logging::LogFilter;
+use crate::particles::Particle;
+use crate::pool::Pooled;
+use crate::schedule::GameSet;
+use crate::waves::Waves;
+
+// F3 shows a corner readout of what the game is doing: frame rate, how many of each kind of
+// entity are alive, how many colliders rapier is checking and where the spawn timer is
+
+#[derive(Component)]
+pub struct DebugOverlay;
+
+pub fn setup_debug_overlay(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(12.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        GlobalZIndex(20),
+        Visibility::Hidden,
+        DebugOverlay,
+    ));
+}
+
+pub fn toggle_debug_overlay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut q_overlay: Query<&mut Visibility, With<DebugOverlay>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F3) {
+        return;
+    }
+    for mut visibility in q_overlay.iter_mut() {
+        visibility.toggle_visible_hidden();
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn update_debug_overlay(
+    mut q_overlay: Query<(&mut Text, &Visibility), With<DebugOverlay>>,
+    diagnostics: Res<DiagnosticsStore>,
+    q_entities: Query<()>,
+    q_enemy: Query<(), (With<Enemy>, Without<ChildOf>, Without<Boss>)>,
+    q_boss: Query<(), With<Boss>>,
+    q_bullet: Query<(), With<Bullet>>,
+    q_particle: Query<(), With<Particle>>,
+    q_pooled: Query<(), With<Pooled>>,
+    q_collider: Query<(), (With<Collider>, Without<ColliderDisabled>)>,
+    spawn_timer: Res<EnemySapwnTimer>,
+    waves: Option<Res<Waves>>,
+    log_filter: Option<Res<LogFilter>>,
+) {
+    let Ok((mut text, visibility)) = q_overlay.single_mut() else {
+        return;
+    };
+    if visibility == Visibility::Hidden {
+        return;
+    }
+
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    let timer = &spawn_timer.0;
+    let mut lines = vec![
+        format!("FPS: {fps:.0}"),
+        format!("Entities: {}", q_entities.iter().count()),
+        format!("Meteors: {}", q_enemy.iter().count()),
+        format!("Bosses: {}", q_boss.iter().count()),
+        format!("Bullets: {}", q_bullet.iter().count()),
+        format!("Particles: {}", q_particle.iter().count()),
+        format!("Pooled: {}", q_pooled.iter().count()),
+        format!("Active colliders: {}", q_collider.iter().count()),
+        format!(
+            "Spawn timer: {:.1}/{:.1}s{}",
+            timer.elapsed_secs(),
+            timer.duration().as_secs_f32(),
+            if timer.paused() { " (paused)" } else { "" },
+        ),
+    ];
+    if let Some(waves) = waves {
+        lines.push(format!("Wave: {}", waves.number));
+    }
+    if let Some(log_filter) = log_filter {
+        lines.push(format!("Log level: {} (F4)", log_filter.level.name()));
+    }
+    text.0 = lines.join("\n");
+}
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin::default());
+        }
+        app.add_systems(Startup, setup_debug_overlay).add_systems(
+            Update,
+            (toggle_debug_overlay, update_debug_overlay)
+                .chain()
+                .in_set(GameSet::Presentation),
+        );
+    }
+}
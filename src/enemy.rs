@@ -1,132 +1,249 @@
+use crate::collision::Layer;
 use crate::config::Config;
+use crate::damage::{DamageDealer, DamageKind, DeathHook, Health, OnDeath};
+use crate::lighting::Light2d;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use bevy_rapier2d::prelude::*;
 use rand::Rng;
+use std::path::PathBuf;
 
-use crate::components_and_resources::{AnimationConfig, ConfigHandle, Enemy, EnemySapwnTimer};
+use crate::boss;
+use crate::components_and_resources::{
+    ConfigHandle, Enemy, EnemySapwnTimer, Level, Player, SpaceStation,
+};
+use crate::console::{self, AddConsoleCommand, ConsoleReply};
+use crate::particles::SoftDotImage;
+use crate::rng::{GameRng, RngStream};
+use crate::schedule::{GameSet, Interpolated};
+use crate::spawning::{self, Entering};
+use crate::sprite_collider::SpriteCollider;
+use crate::waves::Waves;
+
+const METEOR_IMPACT_DAMAGE: f32 = 10.0;
+// half the width of the meteor image (rock1.png is 2048px), times the scale gives the radius
+const METEOR_IMAGE_RADIUS: f32 = 1024.0;
+const CONSOLE_METEOR_SCALE: f32 = 0.035;
+const CONSOLE_METEOR_SPEED: f32 = 100.0;
+
+fn meteor_death_hooks() -> OnDeath {
+    OnDeath(vec![
+        DeathHook::Sprite {
+            path: PathBuf::from("collision_smoke1.png"),
+            scale: 0.5,
+        },
+        DeathHook::Effect("meteor_debris".to_string()),
+        DeathHook::Flash {
+            light: Light2d {
+                color: Color::srgb(1.0, 0.6, 0.25),
+                radius: 220.0,
+                intensity: 2.0,
+            },
+            seconds: 0.35,
+        },
+        DeathHook::Sound {
+            path: PathBuf::from("explosion.ogg"),
+            volume: 5.0,
+        },
+        DeathHook::Score(1),
+        DeathHook::Despawn,
+    ])
+}
+
+/// Everything needed to put a meteor in the world once its warning is over.
+pub struct MeteorSpawn {
+    pub image: Handle<Image>,
+    pub position: Vec2,
+    pub direction: Vec2,
+    pub speed: f32,
+    pub rotation: f32,
+    pub scale: f32,
+    /// Starts off-screen, see `spawning::Entering`.
+    pub entering: bool,
+}
+
+pub fn spawn_meteor(commands: &mut Commands, meteor: MeteorSpawn) -> Entity {
+    // bigger meteors take more hits, 50 health at the smallest size up to 100
+    let health = (meteor.scale * 2000.0).round();
+    let mut entity = commands.spawn((
+        Sprite::from_image(meteor.image),
+        Transform::from_translation(meteor.position.extend(0.0))
+            .with_scale(Vec3::splat(meteor.scale)),
+        Health::new(health),
+        DamageDealer {
+            amount: METEOR_IMPACT_DAMAGE,
+            kind: DamageKind::Impact,
+        },
+        meteor_death_hooks(),
+        Enemy {
+            direction: meteor.direction.extend(0.0),
+            speed: meteor.speed,
+            enemy_rotation: meteor.rotation,
+        },
+        RigidBody::Dynamic,
+        Interpolated::default(),
+        SpriteCollider,
+        GravityScale(0.0),
+        // direction, speed and rotation are only used to kick the meteor off, rapier
+        // owns the motion from here on
+        Velocity {
+            linvel: meteor.direction * meteor.speed,
+            angvel: meteor.rotation,
+        },
+        Damping {
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+        },
+        Restitution {
+            coefficient: 1.0,
+            combine_rule: CoefficientCombineRule::Max,
+        },
+        Friction {
+            coefficient: 0.0,
+            combine_rule: CoefficientCombineRule::Min,
+        },
+        Ccd::enabled(),
+    ));
+    if meteor.entering {
+        entity.insert((
+            Layer::Enemy.groups_ignoring(Layer::Wall),
+            Entering {
+                radius: METEOR_IMAGE_RADIUS * meteor.scale,
+            },
+        ));
+    } else {
+        entity.insert(Layer::Enemy.groups());
+    }
+    entity.id()
+}
+
+/// Puts an enemy straight at `x`, `y` with no warning. Meteors head for the middle of the
+/// screen, bosses fly in and sweep around the point.
+#[allow(clippy::too_many_arguments)]
+fn spawn_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    config_handle: Res<ConfigHandle>,
+    config_assets: Res<Assets<Config>>,
+    asset_server: Res<AssetServer>,
+    soft_dot: Res<SoftDotImage>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    level: Res<Level>,
+) -> ConsoleReply {
+    let kind: String = console::arg(&args, 0, "enemy type")?;
+    let position = Vec2::new(console::arg(&args, 1, "x")?, console::arg(&args, 2, "y")?);
+    match kind.as_str() {
+        "meteor" => {
+            let scale = console::optional_arg(&args, 3, "scale")?.unwrap_or(CONSOLE_METEOR_SCALE);
+            let config = config_assets
+                .get(&config_handle.0)
+                .ok_or("config.toml isn't loaded yet")?;
+            let meteor = spawn_meteor(
+                &mut commands,
+                MeteorSpawn {
+                    image: asset_server.load(config.assets.meteor.clone()),
+                    position,
+                    direction: (-position).normalize_or(Vec2::NEG_Y),
+                    speed: CONSOLE_METEOR_SPEED,
+                    rotation: 1.0,
+                    scale,
+                    entering: false,
+                },
+            );
+            Ok(format!("meteor {meteor} at {position}"))
+        }
+        "boss" => {
+            let index = console::optional_arg(&args, 3, "boss number")?.unwrap_or(level.0 as usize);
+            let win = q_window.single().map_err(|_| "there is no window")?;
+            let (boss, name) = boss::spawn_boss_entity(
+                &mut commands,
+                index,
+                Some(position),
+                win,
+                &asset_server,
+                &soft_dot,
+            );
+            Ok(format!("{name} {boss} flying in to {position}"))
+        }
+        _ => Err(format!("can't spawn a {kind}")),
+    }
+}
 
 pub struct EnemyPlugin;
-#[allow(unused_variables, clippy::too_many_arguments)]
 impl EnemyPlugin {
+    /// Picks where the next meteor comes from with the wave's spawn strategy and puts up its
+    /// warning, the meteor itself arrives when the warning is over.
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     pub fn spawn_enemies(
         mut commands: Commands,
         config_handle: Res<ConfigHandle>,
         config_assets: Res<Assets<Config>>,
-        meshes: ResMut<Assets<Mesh>>,
-        materials: ResMut<Assets<ColorMaterial>>,
         asset_server: Res<AssetServer>,
-        texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
+        soft_dot: Res<SoftDotImage>,
         q_window: Query<&Window, With<PrimaryWindow>>,
+        q_protected: Query<&GlobalTransform, Or<(With<Player>, With<SpaceStation>)>>,
         mut timer: ResMut<EnemySapwnTimer>,
+        mut game_rng: ResMut<GameRng>,
+        waves: Res<Waves>,
         time: Res<Time>,
     ) {
-        let config;
-        if let Some(cfg) = config_assets.get(&config_handle.0) {
-            config = cfg;
-        } else {
+        // boss waves are the boss alone
+        if waves.is_boss_wave() {
             return;
         }
-        if timer.0.tick(time.delta()).just_finished() {
-            let mut rng = rand::rng();
-            let win = q_window.single().unwrap();
-            let win_length = win.size().x;
-            let win_height = win.size().y;
-            let enemy_direction = Vec3::new(
-                rng.random_range(-1.0..1.0),
-                rng.random_range(-1.0..1.0),
-                0.0,
-            )
-            .normalize();
-            let enemy_speed = rng.random_range(50.0..200.0);
-            let rot = rng.random_range(-4.0..4.0);
-            let scale = Vec3::splat(rng.random_range(0.025..0.05));
-
-            commands.spawn((
-                Sprite::from_image(asset_server.load(config.assets.meteor.clone())),
-                Transform::from_xyz(
-                    rng.random_range(-win_length / 2.0 + 50.0..win_length / 2.0 - 50.0),
-                    rng.random_range(-win_height / 2.0 + 50.0..win_height / 2.0 - 50.0),
-                    0.0,
-                )
-                .with_scale(scale),
-                Enemy {
-                    health: 100.0,
-                    direction: enemy_direction,
-                    speed: enemy_speed,
-                    //speed: 0.0,
-                    enemy_rotation: rot,
-                },
-                RigidBody::Dynamic,
-                Collider::ball(500.0),
-                GravityScale(0.0),
-                Restitution::coefficient(1.0),
-                Friction::coefficient(0.20),
-                Ccd::enabled(),
-                //Sensor,
-            ));
-        }
-    }
-
-    pub fn move_enemies(
-        mut query: Query<(&mut Transform, &mut Enemy), With<Enemy>>,
-        time: Res<Time>,
-        q_window: Query<&Window, With<PrimaryWindow>>,
-    ) {
-        let win = q_window.single().unwrap();
-        let time_step = time.delta_secs();
-        for (mut transform, mut enemy) in query.iter_mut() {
-            if transform.translation.x >= win.size().x / 2.0 - 25.0
-                || transform.translation.x <= -win.size().x / 2.0 + 25.0
-            {
-                enemy.direction.x *= -1.0;
-            }
-            if transform.translation.y >= win.size().y / 2.0 - 25.0
-                || transform.translation.y <= -win.size().y / 2.0 + 25.0
-            {
-                enemy.direction.y *= -1.0;
-            }
-            let enemy_direction = enemy.direction;
-            let enemy_speed = enemy.speed;
-            transform.translation += enemy_direction * enemy_speed * time_step;
-        }
-    }
-
-    pub fn rotate_enemies(
-        mut q_enemies: Query<(&mut Transform, &Enemy), With<Enemy>>,
-        timer: Res<Time>,
-    ) {
-        for (mut transform, enemy) in q_enemies.iter_mut() {
-            transform.rotate_z(enemy.enemy_rotation * timer.delta_secs());
+        let Some(config) = config_assets.get(&config_handle.0) else {
+            return;
+        };
+        if !timer.0.tick(time.delta()).just_finished() {
+            return;
         }
-    }
+        let Ok(win) = q_window.single() else {
+            return;
+        };
+        let rng = game_rng.stream(RngStream::Spawns);
+        let scale = rng.random_range(0.025..0.05);
+        let radius = METEOR_IMAGE_RADIUS * scale;
+        let protected: Vec<Vec2> = q_protected
+            .iter()
+            .map(|transform| transform.translation().truncate())
+            .collect();
+        let spawning = &config.spawning;
+        let placement = spawning.strategy(waves.number).place(
+            rng,
+            win.size() / 2.0,
+            radius,
+            waves.number,
+            &protected,
+            spawning.safe_radius,
+        );
 
-    #[allow(dead_code)]
-    pub fn execute_animations_enemies(
-        time: Res<Time>,
-        mut q_enemy: Query<(&mut AnimationConfig, &mut Sprite), With<Enemy>>,
-    ) {
-        for (mut config, mut sprite) in q_enemy.iter_mut() {
-            config.frame_timer.tick(time.delta());
-            if config.frame_timer.just_finished() {
-                if let Some(atlas) = &mut sprite.texture_atlas {
-                    if atlas.index == config.last_sprite_index - 1 {
-                        atlas.index = config.first_sprite_index;
-                    } else {
-                        atlas.index += 1;
-                        config.frame_timer =
-                            AnimationConfig::timer_from_fps(config.fps, String::from("Repeating"));
-                    }
-                }
-            }
-        }
+        let meteor = MeteorSpawn {
+            image: asset_server.load(config.assets.meteor.clone()),
+            position: placement.position,
+            direction: placement.direction,
+            speed: rng.random_range(50.0..200.0),
+            rotation: rng.random_range(-4.0..4.0),
+            scale,
+            entering: placement.offscreen,
+        };
+        spawning::telegraph(
+            &mut commands,
+            &soft_dot,
+            meteor,
+            &placement,
+            radius,
+            spawning.warning_seconds,
+        );
     }
 }
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, Self::spawn_enemies)
-            .add_systems(Update, Self::move_enemies)
-            .add_systems(Update, Self::rotate_enemies);
+        app.add_systems(FixedUpdate, Self::spawn_enemies.in_set(GameSet::Simulation))
+            .add_run_command(
+                "spawn",
+                "spawn <meteor|boss> <x> <y> [meteor scale or boss number]",
+                spawn_command,
+            );
     }
 }
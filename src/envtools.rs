@@ -1,26 +1,33 @@
 use bevy_rapier2d::prelude::*;
 use std::path::PathBuf;
 
-use crate::components_and_resources::{Accuracy, Bullet, ConfigHandle, Enemy, Player, Score, Wall};
+use crate::collision::{BulletHitEnemy, BulletHitPlayer, BulletHitWall, EnemyHitPlayer, Layer};
+use crate::components_and_resources::{
+    Accuracy, Bullet, ConfigHandle, Enemy, GameOverScreen, Player, Score, Smoke, Wall, WallSide,
+};
 use crate::config::Config;
-use crate::utility;
+use crate::console::ConsoleReply;
+use crate::damage::{DamageDealer, DamageEvent, DeathEvent};
+use crate::pool::EntityPool;
+use crate::rng::GameRng;
+use crate::{particles, utility};
 use bevy::prelude::*;
-use bevy::window::PrimaryWindow;
+use bevy::window::{PrimaryWindow, WindowResized};
 
+const CONFIG_FILE: &str = "config.toml";
+
+#[cfg(feature = "dev-tools")]
 pub fn debug_inputs(
     mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    _exit: EventWriter<AppExit>,
 ) {
-    let mut primary_window = q_windows.single_mut().unwrap();
-    if keyboard_input.pressed(KeyCode::F1) {
-        primary_window.cursor_options.visible = false;
-    }
-    if keyboard_input.pressed(KeyCode::F2) {
-        primary_window.cursor_options.visible = true;
-    }
-    if keyboard_input.pressed(KeyCode::Escape) {
-        std::process::exit(0);
+    if let Ok(mut primary_window) = q_windows.single_mut() {
+        if keyboard_input.pressed(KeyCode::F1) {
+            primary_window.cursor_options.visible = false;
+        }
+        if keyboard_input.pressed(KeyCode::F2) {
+            primary_window.cursor_options.visible = true;
+        }
     }
 }
 
@@ -31,7 +38,9 @@ pub fn collision_bullet_enemy(
     mut commands: Commands,
     mut q_score: Query<&mut Score>,
 ) {
-    let mut score = q_score.single_mut().unwrap();
+    let Ok(mut score) = q_score.single_mut() else {
+        return;
+    };
     for (transform_e, _enemy, entity_e) in q_enemy.iter_mut() {
         for (transform_b, _bullet, entity_b) in q_bullet.iter_mut() {
             let right_bound = transform_e.translation.x + 25.0 >= transform_b.translation.x;
@@ -153,167 +162,231 @@ pub fn collision_bullet_enemy(
 /// Handles collisions between bullets and walls.
 pub fn handle_bullet_wall_collision(
     mut commands: Commands,
-    mut collision_events: EventReader<CollisionEvent>,
-    q_bullet: Query<Entity, With<Bullet>>,
-    q_wall: Query<Entity, With<Wall>>,
+    mut bullet_hit_wall: EventReader<BulletHitWall>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
 ) {
-    for event in collision_events.read() {
-        dbg!("collision event detected");
-        if let CollisionEvent::Started(entity1, entity2, _) = event {
-            let bullet_to_despawn =
-                if q_bullet.get(*entity1).is_ok() && q_wall.get(*entity2).is_ok() {
-                    Some(*entity1)
-                } else if q_bullet.get(*entity2).is_ok() && q_wall.get(*entity1).is_ok() {
-                    Some(*entity2)
-                } else {
-                    None
-                };
+    for hit in bullet_hit_wall.read() {
+        // --- Collision Logic ---
+        bullet_pool.release(&mut commands, hit.bullet);
+        trace!(bullet = ?hit.bullet, wall = ?hit.wall, "bullet hit a wall");
+    }
+}
 
-            if let Some(bullet_entity) = bullet_to_despawn {
-                // --- Collision Logic ---
-                commands.entity(bullet_entity).despawn();
-                dbg!("bullet despawned");
-            }
+/// Handles enemy projectiles hitting the player.
+pub fn handle_bullet_player_collision(
+    mut commands: Commands,
+    mut bullet_hit_player: EventReader<BulletHitPlayer>,
+    q_dealer: Query<&DamageDealer>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+    mut damage: EventWriter<DamageEvent>,
+) {
+    for hit in bullet_hit_player.read() {
+        if let Ok(dealer) = q_dealer.get(hit.bullet) {
+            damage.write(DamageEvent::from_dealer(dealer, hit.bullet, hit.player));
         }
+        bullet_pool.release(&mut commands, hit.bullet);
     }
 }
 
 /// Handles collisions between the player and enemies.
 pub fn handle_player_enemy_collision(
     mut commands: Commands,
-    mut collision_events: EventReader<CollisionEvent>,
-    q_player: Query<Entity, With<Player>>,
-    q_enemy: Query<Entity, With<Enemy>>,
+    mut enemy_hit_player: EventReader<EnemyHitPlayer>,
+    q_player: Query<&Transform, With<Player>>,
+    q_dealer: Query<&DamageDealer>,
+    mut smoke_pool: ResMut<EntityPool<Smoke>>,
+    mut damage: EventWriter<DamageEvent>,
     asset_server: Res<AssetServer>,
 ) {
-    for event in collision_events.read() {
-        if let CollisionEvent::Started(entity1, entity2, _) = event {
-            let is_player_enemy_collision = (q_player.get(*entity1).is_ok()
-                && q_enemy.get(*entity2).is_ok())
-                || (q_player.get(*entity2).is_ok() && q_enemy.get(*entity1).is_ok());
-
-            if is_player_enemy_collision {
-                // --- Collision Logic ---
-
-                // Spawn a visual effect at the player's location (or origin)
-                let trans = Transform::from_xyz(0.0, 0.0, 0.0).with_scale(Vec3::splat(0.75));
-                utility::spawn_sprite(
-                    &mut commands,
-                    asset_server.clone(),
-                    PathBuf::from("collision_smoke1.png"),
-                    trans,
-                );
+    for hit in enemy_hit_player.read() {
+        // --- Collision Logic ---
+        if let Ok(dealer) = q_dealer.get(hit.enemy) {
+            damage.write(DamageEvent::from_dealer(dealer, hit.enemy, hit.player));
+        }
 
-                // Here you would typically send a GameOver event or change game state
-                // instead of just printing.
-                println!("Game Over!");
-            }
+        if let Ok(player_transform) = q_player.get(hit.player) {
+            particles::spawn_effect(
+                &mut commands,
+                "station_sparks",
+                Transform::from_translation(player_transform.translation),
+            );
         }
+
+        // Spawn a visual effect at the player's location (or origin)
+        let trans = Transform::from_xyz(0.0, 0.0, 0.0).with_scale(Vec3::splat(0.75));
+        utility::spawn_sprite(
+            &mut commands,
+            &mut smoke_pool,
+            asset_server.clone(),
+            PathBuf::from("collision_smoke1.png"),
+            trans,
+        );
     }
 }
 
 /// Handles collisions between bullets and enemies.
 pub fn handle_bullet_enemy_collision(
     mut commands: Commands,
-    mut collision_events: EventReader<CollisionEvent>,
-    q_bullet: Query<Entity, With<Bullet>>,
-    q_enemy: Query<(Entity, &Transform), With<Enemy>>,
-    mut q_score: Query<&mut Score>,
+    mut bullet_hit_enemy: EventReader<BulletHitEnemy>,
     mut q_accuracy: Query<&mut Accuracy>,
-    asset_server: Res<AssetServer>,
+    q_dealer: Query<&DamageDealer>,
+    mut bullet_pool: ResMut<EntityPool<Bullet>>,
+    mut damage: EventWriter<DamageEvent>,
 ) {
-    let mut score = q_score.single_mut().unwrap();
-    let mut accuracy = q_accuracy.single_mut().unwrap();
-
-    for event in collision_events.read() {
-        if let CollisionEvent::Started(entity1, entity2, _) = event {
-            // Determine which entity is the bullet and which is the enemy
-            let (enemy_entity, enemy_transform, bullet_entity) =
-                if let (Ok(bullet_e), Ok((enemy_e, enemy_t))) =
-                    (q_bullet.get(*entity1), q_enemy.get(*entity2))
-                {
-                    (enemy_e, enemy_t, bullet_e)
-                } else if let (Ok(bullet_e), Ok((enemy_e, enemy_t))) =
-                    (q_bullet.get(*entity2), q_enemy.get(*entity1))
-                {
-                    (enemy_e, enemy_t, bullet_e)
-                } else {
-                    continue; // Not a bullet-enemy collision
-                };
+    // hits still land when the HUD is gone, they just aren't counted
+    let mut accuracy = q_accuracy.single_mut().ok();
 
-            // --- Collision Logic ---
+    for hit in bullet_hit_enemy.read() {
+        // --- Collision Logic ---
+        if let Ok(dealer) = q_dealer.get(hit.bullet) {
+            damage.write(DamageEvent::from_dealer(dealer, hit.bullet, hit.enemy));
+        }
+        bullet_pool.release(&mut commands, hit.bullet);
+        if let Some(accuracy) = accuracy.as_mut() {
+            accuracy.bullets_hit += 1.0;
+        }
+    }
+}
 
-            // Despawn entities
-            commands.entity(bullet_entity).despawn();
-            commands.entity(enemy_entity).despawn();
-            dbg!("bullet & enemy despawned");
+/// Shows the game over screen with the run's score and seed, so the run can be replayed.
+pub fn handle_player_death(
+    mut commands: Commands,
+    mut deaths: EventReader<DeathEvent>,
+    q_player: Query<(), With<Player>>,
+    q_score: Query<&Score>,
+    q_screen: Query<(), With<GameOverScreen>>,
+    game_rng: Res<GameRng>,
+) {
+    for death in deaths.read() {
+        if !q_player.contains(death.entity) || !q_screen.is_empty() {
+            continue;
+        }
+        let score = q_score.iter().next().map_or(0, |score| score.score);
+        info!(score, seed = %game_rng.describe(), "game over");
+        commands
+            .spawn((
+                Node {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.0),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                GlobalZIndex(10),
+                GameOverScreen,
+            ))
+            .with_children(|screen| {
+                for (text, font_size) in [
+                    ("Game Over".to_string(), 90.0),
+                    (format!("Score: {score}"), 45.0),
+                    (format!("Seed: {}", game_rng.describe()), 32.0),
+                ] {
+                    screen.spawn((
+                        Text::new(text),
+                        TextFont {
+                            font_size,
+                            ..default()
+                        },
+                    ));
+                }
+            });
+        break;
+    }
+}
 
-            // Spawn collision effects
-            let effect_transform = Transform::from_translation(enemy_transform.translation)
-                .with_scale(Vec3::splat(0.5));
-            utility::spawn_sprite(
-                &mut commands,
-                asset_server.clone(),
-                PathBuf::from("collision_smoke1.png"),
-                effect_transform,
-            );
-            dbg!("effect spawned");
-            utility::spawn_audio(
-                &mut commands,
-                asset_server.clone(),
-                PathBuf::from("explosion.ogg"),
-                5.0,
-                2.0,
-            );
-            dbg!("sound effet for explosion spawned");
+const WALL_HALF_THICKNESS: f32 = 50.0;
 
-            // Update score and accuracy
-            score.score += 1;
-            accuracy.bullets_hit += 1.0;
-            dbg!("Score and Accuracy updated");
-        }
+// walls sit just outside the visible area so meteors bounce off the screen edges
+fn wall_placement(side: WallSide, win_size: Vec2) -> (Vec3, Collider) {
+    let half = win_size / 2.0;
+    match side {
+        WallSide::Left => (
+            Vec3::new(-half.x - WALL_HALF_THICKNESS, 0.0, 0.0),
+            Collider::cuboid(WALL_HALF_THICKNESS, half.y + 2.0 * WALL_HALF_THICKNESS),
+        ),
+        WallSide::Right => (
+            Vec3::new(half.x + WALL_HALF_THICKNESS, 0.0, 0.0),
+            Collider::cuboid(WALL_HALF_THICKNESS, half.y + 2.0 * WALL_HALF_THICKNESS),
+        ),
+        WallSide::Top => (
+            Vec3::new(0.0, half.y + WALL_HALF_THICKNESS, 0.0),
+            Collider::cuboid(half.x + 2.0 * WALL_HALF_THICKNESS, WALL_HALF_THICKNESS),
+        ),
+        WallSide::Bottom => (
+            Vec3::new(0.0, -half.y - WALL_HALF_THICKNESS, 0.0),
+            Collider::cuboid(half.x + 2.0 * WALL_HALF_THICKNESS, WALL_HALF_THICKNESS),
+        ),
     }
 }
 
-pub fn setup_bounds(mut commands: Commands, mut q_window: Query<&Window, With<PrimaryWindow>>) {
-    let _win = q_window.single_mut().unwrap();
+pub fn setup_bounds(mut commands: Commands, q_window: Query<&Window, With<PrimaryWindow>>) {
+    // without a window yet the walls start at the default size, fit_bounds_to_window moves
+    // them once it reports its real size
+    let win_size = q_window
+        .single()
+        .map_or_else(|_| Window::default().size(), |win| win.size());
 
-    let right_mid = 640.0 * 2.0;
-    let left_mid = -640.0 * 2.0;
-    let top_mid = 360.0 * 2.0;
-    let bottom_mid = -360.0 * 2.0;
+    for side in [
+        WallSide::Left,
+        WallSide::Right,
+        WallSide::Top,
+        WallSide::Bottom,
+    ] {
+        let (translation, collider) = wall_placement(side, win_size);
+        commands.spawn((
+            RigidBody::Fixed,
+            collider,
+            Transform::from_translation(translation),
+            Friction::coefficient(0.0),
+            Restitution::coefficient(1.0),
+            Layer::Wall.groups(),
+            Wall,
+            side,
+        ));
+    }
+}
 
-    commands.spawn((
-        RigidBody::KinematicPositionBased,
-        Collider::cuboid(100.0, 720.0 * 2.0 + 25.0),
-        Transform::from_xyz(right_mid, 0.0, 0.0),
-        Friction::coefficient(0.0),
-        Restitution::coefficient(0.0),
-        ColliderMassProperties::Density(2.0),
-        //Transform::from_xyz(right_mid, 0.0, 0.0),
-        Wall,
-    ));
-    commands.spawn((
-        RigidBody::Fixed,
-        Collider::cuboid(10.0, 720.0 * 2.0 + 25.0),
-        Transform::from_xyz(left_mid, 0.0, 0.0),
-        Wall,
-    ));
-    commands.spawn((
-        RigidBody::Fixed,
-        Collider::cuboid(1240.0 * 2.0 + 25.0, 10.0),
-        Transform::from_xyz(0.0, top_mid, 0.0),
-        Wall,
-    ));
-    commands.spawn((
-        RigidBody::Fixed,
-        Collider::cuboid(1240.0 * 2.0 + 25.0, 10.0),
-        Transform::from_xyz(0.0, bottom_mid, 0.0),
-        Wall,
-    ));
+/// Keeps the walls on the window edges, the window size at startup is not final on every
+/// platform (fullscreen, android rotation).
+pub fn fit_bounds_to_window(
+    mut resize_events: EventReader<WindowResized>,
+    mut q_wall: Query<(&mut Transform, &mut Collider, &WallSide), With<Wall>>,
+) {
+    let Some(resized) = resize_events.read().last() else {
+        return;
+    };
+    let win_size = Vec2::new(resized.width, resized.height);
+    for (mut transform, mut collider, side) in q_wall.iter_mut() {
+        let (translation, new_collider) = wall_placement(*side, win_size);
+        transform.translation = translation;
+        *collider = new_collider;
+    }
 }
 
 pub fn setup_config_file(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let handle_config_file: Handle<Config> = asset_server.load(PathBuf::from("config.toml"));
+    let handle_config_file: Handle<Config> = asset_server.load(PathBuf::from(CONFIG_FILE));
     commands.insert_resource(ConfigHandle(handle_config_file));
 }
+
+/// Reads config.toml again, systems pick the new values up the next time they look.
+pub fn reload_config(In(_): In<Vec<String>>, asset_server: Res<AssetServer>) -> ConsoleReply {
+    asset_server.reload(CONFIG_FILE);
+    Ok(format!("reloading {CONFIG_FILE}"))
+}
+
+#[cfg(feature = "dev-tools")]
+pub fn toggle_physics_debug(
+    In(_): In<Vec<String>>,
+    debug_render: Option<ResMut<DebugRenderContext>>,
+) -> ConsoleReply {
+    let mut debug_render = debug_render.ok_or("the physics debug render isn't available")?;
+    debug_render.enabled = !debug_render.enabled;
+    Ok(format!(
+        "physics debug render {}",
+        if debug_render.enabled { "on" } else { "off" }
+    ))
+}
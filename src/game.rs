@@ -5,41 +5,121 @@ use bevy_rapier2d::prelude::*;
 
 use crate::asset_loader::ConfigLoader;
 use crate::config::Config;
-use crate::{components_and_resources, enemy, envtools, game_plugin, player};
+use crate::console::AddConsoleCommand;
+use crate::{
+    animation, background, boss, bullet_pattern, camera_fx, collision, components_and_resources,
+    damage, damage_feedback, enemy, envtools, game_plugin, headless, lighting, particles, pause,
+    player, pool, preload, replay, rng, schedule, spawning, sprite_collider, waves,
+};
+#[cfg(feature = "dev-tools")]
+use crate::{console, debug_overlay, logging};
 
 pub fn run() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                mode: WindowMode::BorderlessFullscreen(MonitorSelection::Primary),
-                ..Default::default()
-            }),
+    if std::env::args().any(|arg| arg == "--headless") {
+        headless::run();
+        return;
+    }
+
+    let mut app = App::new();
+    let plugins = DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            mode: WindowMode::BorderlessFullscreen(MonitorSelection::Primary),
             ..Default::default()
-        }))
-        //.add_plugins(DefaultPlugins)
-        .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .add_plugins(RapierDebugRenderPlugin::default())
-        .insert_resource(components_and_resources::EnemySapwnTimer(
-            Timer::from_seconds(2.0, TimerMode::Repeating),
-        ))
-        //.add_plugins(bevy::diagnostic::FrameTimeDiagnosticsPlugin::default())
+        }),
+        ..Default::default()
+    });
+    // the F4 log level's filter has to go in with the logger
+    #[cfg(feature = "dev-tools")]
+    let plugins = plugins.set(logging::log_plugin());
+    app.add_plugins(plugins);
+    //.add_plugins(DefaultPlugins)
+    add_gameplay(&mut app);
+    app.add_plugins(pause::PausePlugin);
+    #[cfg(feature = "dev-tools")]
+    add_dev_tools(&mut app);
+    app.run();
+}
+
+/// Debug render, diagnostics, overlay, console and debug keys, only built with dev-tools.
+#[cfg(feature = "dev-tools")]
+fn add_dev_tools(app: &mut App) {
+    app.add_plugins(RapierDebugRenderPlugin::default())
         .add_plugins(bevy::diagnostic::LogDiagnosticsPlugin::default())
-        .insert_resource(components_and_resources::BulletFadeTimer(
-            Timer::from_seconds(1.0, TimerMode::Repeating),
-        ))
-        .add_plugins((
-            game_plugin::GamePlugin,
-            player::PlayerPlugin,
-            enemy::EnemyPlugin,
-        ))
-        .init_asset::<Config>()
-        .init_asset_loader::<ConfigLoader>()
-        .add_systems(Startup, envtools::setup_config_file)
-        .add_systems(Update, envtools::handle_bullet_wall_collision)
-        .add_systems(Update, envtools::handle_player_enemy_collision)
-        .add_systems(Update, envtools::handle_bullet_enemy_collision)
-        .add_systems(Startup, envtools::setup_bounds)
-        .add_systems(Update, envtools::debug_inputs)
-        //.add_systems(Update, envtools::collision_reader)
-        .run();
+        .add_plugins(logging::LoggingPlugin)
+        .add_plugins(debug_overlay::DebugOverlayPlugin)
+        .add_plugins(console::ConsolePlugin)
+        .add_console_command(
+            "physics_debug",
+            "physics_debug",
+            envtools::toggle_physics_debug,
+        )
+        .add_systems(Update, envtools::debug_inputs);
+    //.add_systems(Update, envtools::collision_reader)
+}
+
+/// Physics and every game plugin, shared by the windowed game and the headless simulation.
+pub fn add_gameplay(app: &mut App) {
+    // physics steps with the rest of the simulation, one fixed step per tick, so replays
+    // play out the same
+    app.insert_resource(TimestepMode::Fixed {
+        dt: (1.0 / replay::TICK_RATE) as f32,
+        substeps: 1,
+    })
+    .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0).in_fixed_schedule())
+    .add_plugins(schedule::SchedulePlugin)
+    .add_plugins(rng::RngPlugin)
+    .add_plugins(replay::ReplayPlugin)
+    .add_plugins(preload::PreloadPlugin)
+    .insert_resource(components_and_resources::EnemySapwnTimer(
+        Timer::from_seconds(2.0, TimerMode::Repeating),
+    ))
+    .add_plugins((
+        game_plugin::GamePlugin,
+        player::PlayerPlugin,
+        enemy::EnemyPlugin,
+    ))
+    .init_asset::<Config>()
+    .init_asset_loader::<ConfigLoader>()
+    .add_systems(Startup, envtools::setup_config_file)
+    .add_run_command("reload_config", "reload_config", envtools::reload_config)
+    .add_plugins(collision::CollisionPlugin)
+    .add_plugins(sprite_collider::SpriteColliderPlugin)
+    .add_plugins(pool::PoolPlugin)
+    .add_plugins(animation::AnimationPlugin)
+    .add_plugins(particles::ParticlePlugin)
+    .add_plugins(camera_fx::CameraFxPlugin)
+    .add_plugins(background::BackgroundPlugin)
+    .add_plugins(lighting::LightingPlugin)
+    .add_plugins(damage::DamagePlugin)
+    .add_plugins(damage_feedback::DamageFeedbackPlugin)
+    .add_plugins(waves::WavePlugin)
+    .add_plugins(boss::BossPlugin)
+    .add_plugins(bullet_pattern::BulletPatternPlugin)
+    .add_plugins(spawning::SpawnPlugin)
+    .add_systems(Startup, envtools::setup_bounds)
+    .add_systems(
+        FixedUpdate,
+        envtools::fit_bounds_to_window.in_set(schedule::GameSet::Simulation),
+    );
+    add_collision_handlers(app);
+}
+
+/// The envtools collision handlers, between collision routing and damage, and the game over
+/// screen after it.
+pub fn add_collision_handlers(app: &mut App) {
+    app.add_systems(
+        FixedUpdate,
+        (
+            (
+                envtools::handle_bullet_wall_collision,
+                envtools::handle_player_enemy_collision,
+                envtools::handle_bullet_player_collision,
+                envtools::handle_bullet_enemy_collision,
+            )
+                .after(collision::route_collisions)
+                .before(damage::apply_damage),
+            envtools::handle_player_death.after(damage::apply_damage),
+        )
+            .in_set(schedule::GameSet::Collision),
+    );
 }
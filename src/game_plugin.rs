@@ -1,7 +1,11 @@
+use crate::camera_fx::{self, CameraRig};
 use crate::components_and_resources::{
-    Accuracy, Cursor, HitSoundBulletMeteor, Score, Smoke, SpaceStation,
+    Accuracy, Cursor, HitSoundBulletMeteor, Music, Score, Smoke, SpaceStation,
 };
-use bevy::core_pipeline::bloom::{BloomCompositeMode, BloomPrefilter};
+use crate::damage::Health;
+use crate::lighting;
+use crate::pool::EntityPool;
+use crate::schedule::GameSet;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
@@ -13,26 +17,14 @@ impl GamePlugin {
         commands.spawn((
             Camera2d,
             Camera {
-                hdr: false,
+                hdr: true,
                 clear_color: ClearColorConfig::Custom(Color::BLACK),
                 ..Default::default()
             },
-            bevy::core_pipeline::bloom::Bloom {
-                intensity: 0.15,
-                low_frequency_boost: 0.315,
-                low_frequency_boost_curvature: 0.475,
-                high_pass_frequency: 0.52,
-                prefilter: BloomPrefilter {
-                    threshold: 0.15,
-                    threshold_softness: 0.23,
-                },
-                composite_mode: BloomCompositeMode::Additive,
-                //max_mip_dimension: 1000,
-                //scale: Vec2::splat(10.0),
-                ..Default::default()
-            },
+            lighting::camera_bloom(),
             bevy::core_pipeline::tonemapping::Tonemapping::AgX,
             bevy::core_pipeline::tonemapping::DebandDither::Enabled,
+            CameraRig::default(),
         ));
     }
 
@@ -43,6 +35,7 @@ impl GamePlugin {
             SpaceStation {
                 rotation_speed: 0.06,
             },
+            Health::new(500.0),
         ));
     }
 
@@ -50,15 +43,9 @@ impl GamePlugin {
         mut q_space_station: Query<(&mut Transform, &SpaceStation), With<SpaceStation>>,
         time: Res<Time>,
     ) {
-        let (mut space_station_transform, space_station) = q_space_station.single_mut().unwrap();
-        space_station_transform.rotate_z(time.delta_secs() * space_station.rotation_speed);
-    }
-
-    pub fn setup_background(asset_server: Res<AssetServer>, mut commands: Commands) {
-        commands.spawn((
-            Sprite::from_image(asset_server.load("SpaceBackground2.png")),
-            Transform::from_xyz(0.0, 0.0, -5.0).with_scale(Vec3::splat(1.0)),
-        ));
+        for (mut space_station_transform, space_station) in q_space_station.iter_mut() {
+            space_station_transform.rotate_z(time.delta_secs() * space_station.rotation_speed);
+        }
     }
 
     pub fn setup_crosshair(asset_server: Res<AssetServer>, mut commands: Commands) {
@@ -108,39 +95,46 @@ impl GamePlugin {
     }
 
     pub fn update_score_text(mut q_text: Query<(&mut Text, &mut Score), With<Score>>) {
-        let (mut text, score) = q_text.single_mut().unwrap();
-        text.0 = format!("Score: {}", score.score);
+        for (mut text, score) in q_text.iter_mut() {
+            text.0 = format!("Score: {}", score.score);
+        }
     }
 
     pub fn update_accuracy_text(mut q_text: Query<(&mut Text, &mut Accuracy), With<Accuracy>>) {
-        let (mut text, accuracy) = q_text.single_mut().unwrap();
-        text.0 = format!(
-            "Accuracy: {}",
-            ((accuracy.bullets_hit / accuracy.bullets_fired) * 100.0) as i32
-        );
+        for (mut text, accuracy) in q_text.iter_mut() {
+            text.0 = format!(
+                "Accuracy: {}",
+                ((accuracy.bullets_hit / accuracy.bullets_fired) * 100.0) as i32
+            );
+        }
     }
 
     #[allow(dead_code)]
     pub fn show_score(q_score: Query<&mut Score>) {
-        let score = q_score.single().unwrap();
-        println!("{}", score.score);
+        let Ok(score) = q_score.single() else {
+            return;
+        };
+        info!(score = score.score);
     }
 
     pub fn custom_cursor(
         q_window: Query<&Window, With<PrimaryWindow>>,
         _asset_server: Res<AssetServer>,
         mut q_cursor: Query<&mut Transform, With<Cursor>>,
+        q_rig: Query<&CameraRig>,
     ) {
-        let win = q_window.single().unwrap();
+        let (Ok(win), Ok(mut cursor_transform)) = (q_window.single(), q_cursor.single_mut()) else {
+            return;
+        };
         let cursor_position = match win.cursor_position() {
             Some(k) => k,
             None => return,
         };
         let win_length = win.size().x;
         let win_height = win.size().y;
-        let mut cursor_transform = q_cursor.single_mut().unwrap();
-        cursor_transform.translation.x = cursor_position.x - win_length / 2.0;
-        cursor_transform.translation.y = win_height / 2.0 - cursor_position.y;
+        let lead = camera_fx::camera_lead(&q_rig);
+        cursor_transform.translation.x = cursor_position.x - win_length / 2.0 + lead.x;
+        cursor_transform.translation.y = win_height / 2.0 - cursor_position.y + lead.y;
         cursor_transform.translation.z = 10.0;
     }
 
@@ -148,12 +142,14 @@ impl GamePlugin {
         commands.spawn((
             AudioPlayer::new(asset_server.load("space_music.ogg")),
             PlaybackSettings::LOOP,
+            Music,
         ));
     }
 
     pub fn despawn_smokes(
         mut q_smoke: Query<(&mut Smoke, Entity, &mut Sprite), With<Smoke>>,
         mut commands: Commands,
+        mut pool: ResMut<EntityPool<Smoke>>,
         time: Res<Time>,
     ) {
         for (mut smoke, entity, mut sprite) in q_smoke.iter_mut() {
@@ -163,7 +159,7 @@ impl GamePlugin {
                 ((smoke.duration.duration().as_secs_f32() - remaining) / 2.0).clamp(0.0, 1.0);
             sprite.color.set_alpha(1.0 - alpha);
             if smoke.duration.just_finished() {
-                commands.entity(entity).despawn();
+                pool.release(&mut commands, entity);
             }
         }
     }
@@ -171,12 +167,13 @@ impl GamePlugin {
     pub fn despawn_hit_sounds_bullet_meteor(
         mut q_sound: Query<(&mut HitSoundBulletMeteor, Entity), With<HitSoundBulletMeteor>>,
         mut commands: Commands,
+        mut pool: ResMut<EntityPool<HitSoundBulletMeteor>>,
         time: Res<Time>,
     ) {
         for (mut sound, entity) in q_sound.iter_mut() {
             sound.duration.tick(time.delta());
             if sound.duration.just_finished() {
-                commands.entity(entity).despawn();
+                pool.release(&mut commands, entity);
             }
         }
     }
@@ -185,18 +182,23 @@ impl GamePlugin {
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, Self::setup_camera)
-            .add_systems(Startup, Self::setup_background)
             .add_systems(Startup, Self::setup_crosshair)
             .add_systems(Startup, Self::setup_score)
             .add_systems(Startup, Self::setup_space_station)
             .add_systems(Startup, Self::setup_music)
             //.add_systems(Startup, setup_progress_bar)
             //.add_systems(Update, reactivity)
-            .add_systems(Update, Self::custom_cursor)
-            .add_systems(Update, Self::update_score_text)
-            .add_systems(Update, Self::rotate_space_station)
-            .add_systems(Update, Self::despawn_smokes)
-            .add_systems(Update, Self::despawn_hit_sounds_bullet_meteor)
-            .add_systems(Update, Self::update_accuracy_text);
+            .add_systems(
+                Update,
+                (
+                    Self::custom_cursor,
+                    Self::update_score_text,
+                    Self::rotate_space_station,
+                    Self::despawn_smokes,
+                    Self::despawn_hit_sounds_bullet_meteor,
+                    Self::update_accuracy_text,
+                )
+                    .in_set(GameSet::Presentation),
+            );
     }
 }
@@ -0,0 +1,390 @@
+use anyhow::{Context, bail};
+use bevy::app::PluginsState;
+use bevy::audio::{AudioLoader, AudioSource};
+use bevy::image::{CompressedImageFormats, ImageLoader, TextureAtlasPlugin};
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::ExitCondition;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::boss::Boss;
+use crate::components_and_resources::{Accuracy, Enemy, Level, Player, Score};
+use crate::damage::{self, Damaged, DeathEvent};
+use crate::game;
+use crate::pool::Pooled;
+use crate::preload::Preload;
+use crate::replay::{self, LiveInput};
+use crate::rng::{GameRng, SeedSource};
+use crate::schedule::GameSet;
+use crate::spawning::Entering;
+use crate::waves::Waves;
+
+// balance testing without a window. `--headless` plays whole runs as fast as the machine allows
+// with a scripted turret and writes what happened as JSON:
+//
+//   advent --headless --minutes 10 --runs 20 --player aim --seed 7 --out stats.json
+//
+// Every run steps one fixed tick per update in an ARENA sized window that never opens. Runs use
+// the seeds seed, seed + 1, ..., so a batch can be repeated exactly. A run ends when its minutes
+// are up or the station is destroyed.
+
+const ARENA: Vec2 = Vec2::new(1920.0, 1080.0);
+const DEFAULT_MINUTES: f64 = 5.0;
+// loading is real work on the IO threads, give up if the art isn't there by then
+const WARMUP_LIMIT: Duration = Duration::from_secs(30);
+// radians per second the sweeping turret turns
+const SWEEP_SPEED: f32 = 1.5;
+const CLICKS_PER_SECOND: f32 = 6.0;
+
+/// Who plays the turret.
+#[derive(Resource, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Autopilot {
+    /// Never fires, measures how long the station lasts on its own.
+    Idle,
+    /// Turns at a steady rate, firing all the time.
+    Sweep,
+    /// Fires at the nearest enemy on screen.
+    Aim,
+}
+
+struct Options {
+    minutes: f64,
+    runs: u32,
+    seed: u64,
+    autopilot: Autopilot,
+    out: Option<PathBuf>,
+}
+
+impl Options {
+    /// `--seed` and `--daily` pick the first run's seed the same way they do for the game.
+    fn from_args() -> anyhow::Result<Self> {
+        let mut options = Options {
+            minutes: DEFAULT_MINUTES,
+            runs: 1,
+            seed: GameRng::from_args().seed(),
+            autopilot: Autopilot::Aim,
+            out: None,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().with_context(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--minutes" => {
+                    options.minutes = value()?.parse().context("--minutes needs a number")?;
+                }
+                "--runs" => {
+                    options.runs = value()?.parse().context("--runs needs a whole number")?;
+                }
+                "--player" => {
+                    options.autopilot = match value()?.as_str() {
+                        "idle" => Autopilot::Idle,
+                        "sweep" => Autopilot::Sweep,
+                        "aim" => Autopilot::Aim,
+                        other => bail!("unknown --player {other}, expected idle, sweep or aim"),
+                    }
+                }
+                "--out" => options.out = Some(value()?.into()),
+                _ => {}
+            }
+        }
+        if options.minutes <= 0.0 || options.runs == 0 {
+            bail!("nothing to simulate, --minutes and --runs must be above zero");
+        }
+        Ok(options)
+    }
+}
+
+/// What happened during one run.
+#[derive(Resource, Serialize, Debug, Default)]
+pub struct RunStats {
+    pub seed: u64,
+    pub seconds_survived: f64,
+    pub died: bool,
+    pub meteors_spawned: u32,
+    pub bosses_spawned: u32,
+    pub meteors_destroyed: u32,
+    pub bosses_destroyed: u32,
+    /// Wave and level the run ended on.
+    pub wave: u32,
+    pub level: u32,
+    pub score: u32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub damage_taken: f32,
+}
+
+#[derive(Serialize)]
+struct Report {
+    autopilot: Autopilot,
+    minutes: f64,
+    deaths: u32,
+    mean_seconds_survived: f64,
+    mean_meteors_destroyed: f64,
+    runs: Vec<RunStats>,
+}
+
+impl Report {
+    fn new(options: &Options, runs: Vec<RunStats>) -> Self {
+        let count = runs.len().max(1) as f64;
+        Self {
+            autopilot: options.autopilot,
+            minutes: options.minutes,
+            deaths: runs.iter().filter(|run| run.died).count() as u32,
+            mean_seconds_survived: runs.iter().map(|run| run.seconds_survived).sum::<f64>() / count,
+            mean_meteors_destroyed: runs
+                .iter()
+                .map(|run| run.meteors_destroyed as f64)
+                .sum::<f64>()
+                / count,
+            runs,
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn fly_autopilot(
+    autopilot: Res<Autopilot>,
+    mut live: ResMut<LiveInput>,
+    q_player: Query<&Transform, With<Player>>,
+    q_enemy: Query<&GlobalTransform, (With<Enemy>, Without<Pooled>, Without<Entering>)>,
+    time: Res<Time>,
+    mut reload: Local<f32>,
+) {
+    *reload -= time.delta_secs();
+    let target = match *autopilot {
+        Autopilot::Idle => None,
+        Autopilot::Sweep => Some(time.elapsed_secs() * SWEEP_SPEED),
+        Autopilot::Aim => {
+            let Ok(player) = q_player.single() else {
+                return;
+            };
+            let position = player.translation.truncate();
+            q_enemy
+                .iter()
+                .map(|enemy| enemy.translation().truncate() - position)
+                .filter(|offset| *offset != Vec2::ZERO)
+                .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+                .map(Vec2::to_angle)
+        }
+    };
+    let Some(aim) = target else {
+        return;
+    };
+    live.aim(aim);
+    // every press fires, so click no faster than a person could
+    if *reload <= 0.0 {
+        live.press_fire();
+        *reload = 1.0 / CLICKS_PER_SECOND;
+    }
+}
+
+pub fn count_spawns(
+    mut stats: ResMut<RunStats>,
+    q_spawned: Query<Has<Boss>, (Added<Enemy>, Without<ChildOf>)>,
+) {
+    for is_boss in q_spawned.iter() {
+        if is_boss {
+            stats.bosses_spawned += 1;
+        } else {
+            stats.meteors_spawned += 1;
+        }
+    }
+}
+
+pub fn count_hits(
+    mut stats: ResMut<RunStats>,
+    mut damaged: EventReader<Damaged>,
+    mut deaths: EventReader<DeathEvent>,
+    q_enemy: Query<Has<Boss>, With<Enemy>>,
+    q_player: Query<(), With<Player>>,
+    time: Res<Time>,
+) {
+    for hit in damaged.read() {
+        if q_player.contains(hit.entity) {
+            stats.damage_taken += hit.amount;
+        }
+    }
+    for death in deaths.read() {
+        if q_player.contains(death.entity) && !stats.died {
+            stats.died = true;
+            stats.seconds_survived = time.elapsed_secs_f64();
+        }
+        match q_enemy.get(death.entity) {
+            Ok(true) => stats.bosses_destroyed += 1,
+            Ok(false) => stats.meteors_destroyed += 1,
+            Err(_) => {}
+        }
+    }
+}
+
+/// The parts of bevy the game needs to run without a window, a GPU or an audio device. Every
+/// update is one fixed tick.
+pub fn add_headless_plugins(app: &mut App) {
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        InputPlugin,
+        // the window is never opened, it only sets the size of the arena
+        WindowPlugin {
+            primary_window: Some(Window {
+                resolution: ARENA.into(),
+                ..default()
+            }),
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        },
+        AssetPlugin::default(),
+        TextureAtlasPlugin,
+    ))
+    // the assets the game loads, without anything to draw or play them
+    .init_asset::<Image>()
+    .register_asset_loader(ImageLoader::new(CompressedImageFormats::NONE))
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .init_asset::<AudioSource>()
+    .init_asset_loader::<AudioLoader>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        1.0 / replay::TICK_RATE,
+    )));
+}
+
+/// Lets plugins finish setting up, so the app can be stepped with `update` instead of `run`.
+pub fn finish_plugins(app: &mut App) {
+    while app.plugins_state() == PluginsState::Adding {
+        bevy::tasks::tick_global_task_pools_on_main_thread();
+    }
+    app.finish();
+    app.cleanup();
+}
+
+fn build_app(seed: u64, autopilot: Autopilot) -> App {
+    let mut app = App::new();
+    add_headless_plugins(&mut app);
+    game::add_gameplay(&mut app);
+    app.insert_resource(GameRng::new(seed, SeedSource::Chosen))
+        .insert_resource(autopilot)
+        .insert_resource(RunStats { seed, ..default() })
+        .add_systems(
+            FixedUpdate,
+            (
+                fly_autopilot
+                    .in_set(GameSet::Input)
+                    .before(replay::next_player_input),
+                (count_spawns, count_hits)
+                    .after(damage::apply_damage)
+                    .before(damage::run_death_hooks)
+                    .in_set(GameSet::Collision),
+            ),
+        );
+    finish_plugins(&mut app);
+    app
+}
+
+/// Runs updates with time stopped until the shared preload is done, so colliders are there
+/// from the first tick on.
+fn warm_up(app: &mut App) -> anyhow::Result<()> {
+    app.world_mut().resource_mut::<Time<Virtual>>().pause();
+    let started = Instant::now();
+    loop {
+        app.update();
+        match app.world().resource::<Preload>() {
+            Preload::Loading => {}
+            Preload::Ready => break,
+            Preload::Failed(err) => {
+                bail!("{err}, start it with `cargo run` or point BEVY_ASSET_ROOT at the project")
+            }
+        }
+        if started.elapsed() > WARMUP_LIMIT {
+            bail!("assets took over {}s to load", WARMUP_LIMIT.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    app.world_mut().resource_mut::<Time<Virtual>>().unpause();
+    Ok(())
+}
+
+fn simulate(seed: u64, options: &Options) -> anyhow::Result<RunStats> {
+    let mut app = build_app(seed, options.autopilot);
+    warm_up(&mut app)?;
+
+    let seconds = options.minutes * 60.0;
+    loop {
+        app.update();
+        let world = app.world();
+        let elapsed = world.resource::<Time<Fixed>>().elapsed_secs_f64();
+        if world.resource::<RunStats>().died || elapsed >= seconds {
+            break;
+        }
+    }
+
+    let world = app.world_mut();
+    let elapsed = world.resource::<Time<Fixed>>().elapsed_secs_f64();
+    let wave = world.resource::<Waves>().number;
+    let level = world.resource::<Level>().0;
+    let score = world
+        .query::<&Score>()
+        .iter(world)
+        .next()
+        .map_or(0, |score| score.score);
+    let (shots_fired, shots_hit) = world
+        .query::<&Accuracy>()
+        .iter(world)
+        .next()
+        .map_or((0, 0), |accuracy| {
+            (accuracy.bullets_fired as u32, accuracy.bullets_hit as u32)
+        });
+    let mut stats = world
+        .remove_resource::<RunStats>()
+        .context("run stats went missing")?;
+    if !stats.died {
+        stats.seconds_survived = elapsed;
+    }
+    stats.wave = wave;
+    stats.level = level;
+    stats.score = score;
+    stats.shots_fired = shots_fired;
+    stats.shots_hit = shots_hit;
+    Ok(stats)
+}
+
+fn simulate_all() -> anyhow::Result<()> {
+    let options = Options::from_args()?;
+    let mut runs = Vec::new();
+    for index in 0..options.runs {
+        let seed = options.seed.wrapping_add(index as u64);
+        let stats = simulate(seed, &options)?;
+        // stdout may be the report, progress goes to stderr
+        eprintln!(
+            "run {}/{}: seed {}, {:.0}s{}, {} meteors destroyed, wave {}",
+            index + 1,
+            options.runs,
+            seed,
+            stats.seconds_survived,
+            if stats.died { " (died)" } else { "" },
+            stats.meteors_destroyed,
+            stats.wave,
+        );
+        runs.push(stats);
+    }
+
+    let json = serde_json::to_string_pretty(&Report::new(&options, runs))?;
+    match &options.out {
+        Some(path) => {
+            std::fs::write(path, json).with_context(|| format!("can't write {}", path.display()))?
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+/// Entry point for `--headless`.
+pub fn run() {
+    if let Err(err) = simulate_all() {
+        eprintln!("headless simulation failed: {err:#}");
+        std::process::exit(1);
+    }
+}
@@ -1,13 +1,40 @@
 mod adventui;
+mod animation;
 mod asset_loader;
+mod background;
+mod boss;
+mod bullet_pattern;
+mod camera_fx;
+mod collision;
 mod components_and_resources;
 mod config;
+mod console;
+mod damage;
+mod damage_feedback;
+#[cfg(feature = "dev-tools")]
+mod debug_overlay;
 mod enemy;
 mod envtools;
 mod game;
 mod game_plugin;
+mod headless;
+mod lighting;
+#[cfg(feature = "dev-tools")]
+mod logging;
+mod particles;
+mod pause;
 mod player;
+mod pool;
+mod preload;
+mod replay;
+mod rng;
+mod schedule;
+mod spawning;
+mod sprite_collider;
+#[cfg(test)]
+mod tests;
 mod utility;
+mod waves;
 use bevy::prelude::*;
 
 #[bevy_main]
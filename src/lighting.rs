@@ -0,0 +1,222 @@
+use bevy::core_pipeline::bloom::{Bloom, BloomCompositeMode, BloomPrefilter};
+use bevy::prelude::*;
+use bevy::render::mesh::MeshTag;
+
+use crate::collision::EnemyHitPlayer;
+use crate::components_and_resources::{ConfigHandle, Player};
+use crate::config::{Config, GraphicsQuality, current_settings};
+use crate::particles::{self, ParticleMaterial, ParticleMaterials, ParticleQuad, SoftDotImage};
+use crate::schedule::GameSet;
+
+// cheap 2D lighting: every light is a soft glow drawn above the playfield with the additive
+// particle material, so whatever is underneath (meteors, the station) gets its colour added to.
+// Colours above 1.0 only mean anything with HDR on, where Bloom spreads them further. Low quality
+// hides the glows and turns HDR and bloom off.
+
+// glows sit above meteors and the player but below particles
+const LIGHT_Z: f32 = 1.5;
+// how much of the light's colour is added to what's under it at full strength
+const LIGHT_STRENGTH: f32 = 0.45;
+
+/// Emissive tint for player bullets, blooms with HDR on and clamps to white without.
+pub const BULLET_EMISSIVE: Color = Color::linear_rgb(2.5, 2.5, 3.5);
+
+/// A point light on the entity, drawn as a glow of `radius` pixels.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Light2d {
+    pub color: Color,
+    pub radius: f32,
+    pub intensity: f32,
+}
+
+/// Fades the light out over its timer, then despawns the entity.
+#[derive(Component)]
+pub struct LightFade(pub Timer);
+
+// the glow quad drawn for a parent's Light2d
+#[derive(Component)]
+pub struct LightGlow;
+
+pub fn camera_bloom() -> Bloom {
+    Bloom {
+        intensity: 0.15,
+        low_frequency_boost: 0.315,
+        low_frequency_boost_curvature: 0.475,
+        high_pass_frequency: 0.52,
+        prefilter: BloomPrefilter {
+            threshold: 0.15,
+            threshold_softness: 0.23,
+        },
+        composite_mode: BloomCompositeMode::Additive,
+        ..Default::default()
+    }
+}
+
+/// Spawns a light flash at `translation` that fades out over `seconds`.
+pub fn spawn_flash(commands: &mut Commands, translation: Vec3, light: Light2d, seconds: f32) {
+    commands.spawn((
+        Transform::from_translation(translation),
+        Visibility::default(),
+        light,
+        LightFade(Timer::from_seconds(seconds, TimerMode::Once)),
+    ));
+}
+
+/// Switches HDR and bloom on the camera to match the quality setting.
+pub fn apply_graphics_quality(
+    mut commands: Commands,
+    mut q_camera: Query<(Entity, &mut Camera, Has<Bloom>), With<Camera2d>>,
+    config_handle: Res<ConfigHandle>,
+    configs: Res<Assets<Config>>,
+) {
+    let high = current_settings(&config_handle, &configs).quality == GraphicsQuality::High;
+    for (entity, mut camera, has_bloom) in q_camera.iter_mut() {
+        if camera.hdr != high {
+            camera.hdr = high;
+        }
+        match (high, has_bloom) {
+            (true, false) => {
+                commands.entity(entity).insert(camera_bloom());
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<Bloom>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Gives every light a glow child. Pooled entities keep theirs between uses.
+pub fn attach_light_glows(
+    mut commands: Commands,
+    q_light: Query<(Entity, &Light2d, Option<&Children>), Changed<Light2d>>,
+    q_glow: Query<(), With<LightGlow>>,
+    soft_dot: Res<SoftDotImage>,
+    quad: Res<ParticleQuad>,
+    mut shared_materials: ResMut<ParticleMaterials>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+) {
+    for (entity, light, children) in q_light.iter() {
+        let has_glow = children.is_some_and(|children| children.iter().any(|c| q_glow.contains(c)));
+        if has_glow {
+            continue;
+        }
+        commands.entity(entity).with_child((
+            Mesh2d(quad.0.clone()),
+            MeshMaterial2d(shared_materials.get(&mut materials, &soft_dot.0, light.intensity)),
+            MeshTag::default(),
+            Transform::default(),
+            LightGlow,
+        ));
+    }
+}
+
+pub fn fade_lights(
+    mut commands: Commands,
+    mut q_light: Query<(Entity, &mut LightFade)>,
+    time: Res<Time>,
+) {
+    for (entity, mut fade) in q_light.iter_mut() {
+        fade.0.tick(time.delta());
+        if fade.0.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Sizes and colours each glow from its light, undoing the parent's scale so `radius` is in
+/// screen pixels whatever sprite the light sits on.
+#[allow(clippy::type_complexity)]
+pub fn update_light_glows(
+    q_light: Query<(&Light2d, &Transform, Option<&LightFade>), Without<LightGlow>>,
+    mut q_glow: Query<
+        (
+            &ChildOf,
+            &mut MeshMaterial2d<ParticleMaterial>,
+            &mut MeshTag,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<LightGlow>,
+    >,
+    soft_dot: Res<SoftDotImage>,
+    mut shared_materials: ResMut<ParticleMaterials>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+    config_handle: Res<ConfigHandle>,
+    configs: Res<Assets<Config>>,
+) {
+    let high = current_settings(&config_handle, &configs).quality == GraphicsQuality::High;
+    for (child_of, mut material, mut tint, mut transform, mut visibility) in q_glow.iter_mut() {
+        let Ok((light, parent_transform, fade)) = q_light.get(child_of.parent()) else {
+            continue;
+        };
+        visibility.set_if_neq(if high {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+        if !high {
+            continue;
+        }
+
+        // fade-outs drop off quickly so flashes read as a pop rather than a slow dim
+        let strength = fade.map_or(1.0, |fade| (1.0 - fade.0.fraction()).powi(2));
+        // the material carries the intensity, pooled lights may come back with another one
+        let shared = shared_materials.get(&mut materials, &soft_dot.0, light.intensity);
+        if material.0 != shared {
+            material.0 = shared;
+        }
+        *tint = particles::particle_tint(
+            light
+                .color
+                .to_linear()
+                .with_alpha(LIGHT_STRENGTH * strength),
+        );
+
+        let scale = parent_transform.scale.max(Vec3::splat(f32::EPSILON));
+        transform.scale = Vec3::new(light.radius * 2.0, light.radius * 2.0, 1.0) / scale;
+        transform.translation = Vec3::new(
+            0.0,
+            0.0,
+            (LIGHT_Z - parent_transform.translation.z) / scale.z,
+        );
+    }
+}
+
+/// Lights up the station when a meteor hits it, explosions flash from their death handler.
+pub fn flash_on_player_hits(
+    mut commands: Commands,
+    mut enemy_hit_player: EventReader<EnemyHitPlayer>,
+    q_player: Query<&Transform, With<Player>>,
+) {
+    for hit in enemy_hit_player.read() {
+        if let Ok(transform) = q_player.get(hit.player) {
+            spawn_flash(
+                &mut commands,
+                transform.translation,
+                Light2d {
+                    color: Color::srgb(1.0, 0.85, 0.5),
+                    radius: 160.0,
+                    intensity: 2.5,
+                },
+                0.25,
+            );
+        }
+    }
+}
+
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                apply_graphics_quality,
+                flash_on_player_hits,
+                (attach_light_glows, fade_lights, update_light_glows).chain(),
+            )
+                .in_set(GameSet::Presentation),
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use bevy::log::tracing_subscriber::{EnvFilter, Registry, reload};
+use bevy::log::{BoxedLayer, DEFAULT_FILTER, Level, LogPlugin};
+use bevy::prelude::*;
+
+// LogPlugin's own filter is fixed once the subscriber is installed, so it lets everything
+// through and a second, reloadable filter in front of it decides what is shown. RUST_LOG
+// still applies to LogPlugin's filter, so it caps what the runtime filter can show.
+
+/// How chatty the game's own modules are, cycled with F4. Other crates stay at info.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn next(self) -> Self {
+        match self {
+            LogLevel::Warn => LogLevel::Info,
+            LogLevel::Info => LogLevel::Debug,
+            LogLevel::Debug => LogLevel::Trace,
+            LogLevel::Trace => LogLevel::Warn,
+        }
+    }
+
+    fn directives(self) -> String {
+        let game = module_path!().split("::").next().unwrap_or_default();
+        match self {
+            LogLevel::Warn => "warn".to_string(),
+            LogLevel::Info => "info".to_string(),
+            LogLevel::Debug => format!("info,{game}=debug"),
+            LogLevel::Trace => format!("info,{game}=trace"),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+}
+
+/// The runtime log filter.
+#[derive(Resource)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    pub level: LogLevel,
+}
+
+impl LogFilter {
+    pub fn set(&mut self, level: LogLevel) {
+        match self.handle.reload(EnvFilter::new(level.directives())) {
+            Ok(()) => {
+                self.level = level;
+                info!("log level {}", level.name());
+            }
+            Err(err) => warn!("can't change the log level: {err}"),
+        }
+    }
+}
+
+/// LogPlugin with the runtime filter in front, for DefaultPlugins.
+pub fn log_plugin() -> LogPlugin {
+    LogPlugin {
+        filter: DEFAULT_FILTER.to_string(),
+        level: Level::TRACE,
+        custom_layer: runtime_filter,
+    }
+}
+
+fn runtime_filter(app: &mut App) -> Option<BoxedLayer> {
+    let level = LogLevel::Info;
+    let (layer, handle) = reload::Layer::new(EnvFilter::new(level.directives()));
+    app.insert_resource(LogFilter { handle, level });
+    Some(Box::new(layer))
+}
+
+pub fn cycle_log_level(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    log_filter: Option<ResMut<LogFilter>>,
+) {
+    let Some(mut log_filter) = log_filter else {
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        let next = log_filter.level.next();
+        log_filter.set(next);
+    }
+}
+
+pub struct LoggingPlugin;
+
+impl Plugin for LoggingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, cycle_log_level);
+    }
+}
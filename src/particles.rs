@@ -0,0 +1,499 @@
+use bevy::asset::RenderAssetUsages;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy::render::mesh::{MeshTag, MeshVertexBufferLayoutRef};
+use bevy::render::render_resource::{
+    AsBindGroup, BlendComponent, BlendFactor, BlendOperation, BlendState, Extent3d,
+    RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError, TextureDimension,
+    TextureFormat,
+};
+use bevy::sprite::{AlphaMode2d, Material2d, Material2dKey, Material2dPlugin};
+use rand::Rng;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::pool::{EntityPool, Pooled};
+use crate::rng::{GameRng, RngStream};
+use crate::schedule::GameSet;
+
+// CPU particles. Emitter presets live in assets/effects.fx.toml. Plain presets are alpha-blended
+// sprites, additive ones are quads drawn with ParticleMaterial, which adds their colour onto the
+// frame so overlapping sparks build up into bright cores with or without HDR. With HDR on,
+// `intensity` pushes them above 1.0 for the camera's Bloom as well. Additive particles share one
+// material per texture and intensity so they batch, each one's colour over its life rides in its
+// MeshTag instead.
+
+/// Piecewise linear curve over a particle's normalised age (0.0 at birth, 1.0 at death).
+#[derive(Debug, Clone)]
+pub struct Curve<const N: usize>(pub Vec<[f32; N]>);
+
+// serde can't derive for const generic arrays, so keys are read as lists and length checked
+impl<'de, const N: usize> Deserialize<'de> for Curve<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let keys = Vec::<Vec<f32>>::deserialize(deserializer)?;
+        keys.into_iter()
+            .map(|key| {
+                <[f32; N]>::try_from(key.as_slice()).map_err(|_| {
+                    serde::de::Error::invalid_length(key.len(), &format!("{N} values").as_str())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Curve)
+    }
+}
+
+impl<const N: usize> Curve<N> {
+    /// Value at `t`, each key is `[t, values...]`.
+    pub fn sample(&self, t: f32) -> [f32; N] {
+        let keys = &self.0;
+        let Some(first) = keys.first() else {
+            return [0.0; N];
+        };
+        if t <= first[0] {
+            return *first;
+        }
+        for pair in keys.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if t <= b[0] {
+                let f = ((t - a[0]) / (b[0] - a[0]).max(f32::EPSILON)).clamp(0.0, 1.0);
+                let mut out = a;
+                for i in 1..N {
+                    out[i] = a[i] + (b[i] - a[i]) * f;
+                }
+                return out;
+            }
+        }
+        *keys.last().unwrap()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct EmitterPreset {
+    /// Sprite used for each particle, a generated soft dot when unset.
+    pub texture: Option<PathBuf>,
+    /// Particles per second while the emitter is alive.
+    #[serde(default)]
+    pub rate: f32,
+    /// Particles spawned at once when the emitter starts.
+    #[serde(default)]
+    pub burst: u32,
+    /// Seconds the emitter lives, forever when unset.
+    pub duration: Option<f32>,
+    /// Min and max particle lifetime in seconds.
+    pub lifetime: [f32; 2],
+    /// Min and max launch speed in pixels per second.
+    pub speed: [f32; 2],
+    /// Full opening angle of the launch cone in degrees, centred on the emitter's +x axis
+    /// rotated by `direction`.
+    #[serde(default = "full_circle")]
+    pub cone: f32,
+    #[serde(default)]
+    pub direction: f32,
+    /// Velocity lost per second, as a fraction.
+    #[serde(default)]
+    pub drag: f32,
+    /// `[t, size]` keys, size in pixels.
+    pub size: Curve<2>,
+    /// `[t, r, g, b, a]` keys.
+    pub color: Curve<5>,
+    /// Adds the particle's colour onto what's behind it instead of blending over it.
+    #[serde(default)]
+    pub additive: bool,
+    /// Multiplies the colour of additive particles.
+    #[serde(default = "default_intensity")]
+    pub intensity: f32,
+    #[serde(default)]
+    pub z: f32,
+}
+
+fn full_circle() -> f32 {
+    360.0
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Debug, Asset, TypePath)]
+pub struct EffectPresets {
+    pub emitters: HashMap<String, EmitterPreset>,
+}
+
+impl EffectPresets {
+    /// Swaps min/max ranges that are the wrong way round and makes cones positive, warning about
+    /// each, so a typo in the data can't crash the emitters.
+    pub fn fix_ranges(&mut self) {
+        for (name, preset) in self.emitters.iter_mut() {
+            for (field, range) in [
+                ("lifetime", &mut preset.lifetime),
+                ("speed", &mut preset.speed),
+            ] {
+                if range.iter().any(|value| !value.is_finite()) {
+                    warn!("effect {name}: {field} {range:?} isn't a range of numbers, using 0");
+                    *range = [0.0, 0.0];
+                } else if range[0] > range[1] {
+                    warn!("effect {name}: {field} {range:?} has min above max, swapping them");
+                    range.swap(0, 1);
+                }
+            }
+            if !preset.cone.is_finite() {
+                warn!(
+                    "effect {name}: cone {} isn't a number, using 360",
+                    preset.cone
+                );
+                preset.cone = full_circle();
+            } else if preset.cone < 0.0 {
+                warn!(
+                    "effect {name}: cone {} is negative, using its size",
+                    preset.cone
+                );
+                preset.cone = -preset.cone;
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct EffectPresetsHandle(pub Handle<EffectPresets>);
+
+#[derive(Resource)]
+pub struct SoftDotImage(pub Handle<Image>);
+
+// the unit quad additive particles are drawn on, scaled to their size
+#[derive(Resource)]
+pub struct ParticleQuad(pub Handle<Mesh>);
+
+/// Draws its texture in each instance's `particle_tint` times `tint`, added onto the frame
+/// instead of blended over it. `tint` carries the intensity, which can go above 1.0.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct ParticleMaterial {
+    #[uniform(0)]
+    pub tint: LinearRgba,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Handle<Image>,
+}
+
+impl Material2d for ParticleMaterial {
+    fn vertex_shader() -> ShaderRef {
+        "shaders/additive_particle.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "shaders/additive_particle.wgsl".into()
+    }
+
+    // drawn with the transparent pass so it sorts with the sprites
+    fn alpha_mode(&self) -> AlphaMode2d {
+        AlphaMode2d::Blend
+    }
+
+    fn specialize(
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: Material2dKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let additive = BlendState {
+            color: BlendComponent {
+                src_factor: BlendFactor::SrcAlpha,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+            alpha: BlendComponent {
+                src_factor: BlendFactor::Zero,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+        };
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            for target in fragment.targets.iter_mut().flatten() {
+                target.blend = Some(additive);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One ParticleMaterial per texture and intensity, shared by everything drawn with them.
+#[derive(Resource, Default)]
+pub struct ParticleMaterials(HashMap<(AssetId<Image>, u32), Handle<ParticleMaterial>>);
+
+impl ParticleMaterials {
+    pub fn get(
+        &mut self,
+        materials: &mut Assets<ParticleMaterial>,
+        texture: &Handle<Image>,
+        intensity: f32,
+    ) -> Handle<ParticleMaterial> {
+        self.0
+            .entry((texture.id(), intensity.to_bits()))
+            .or_insert_with(|| {
+                materials.add(ParticleMaterial {
+                    tint: LinearRgba::rgb(intensity, intensity, intensity),
+                    texture: texture.clone(),
+                })
+            })
+            .clone()
+    }
+}
+
+/// A colour for one ParticleMaterial instance, 8 bits a channel, clamped to 0.0..=1.0.
+pub fn particle_tint(color: impl Into<LinearRgba>) -> MeshTag {
+    let channels = color.into().to_f32_array();
+    MeshTag(channels.iter().rev().fold(0, |packed, channel| {
+        packed << 8 | (channel.clamp(0.0, 1.0) * 255.0).round() as u32
+    }))
+}
+
+/// Emits particles of `preset` from the entity's global position and rotation. Put it on a
+/// moving entity for trails, or on its own entity for one-shot effects.
+#[derive(Component)]
+pub struct ParticleEmitter {
+    pub preset: String,
+    elapsed: f32,
+    accumulator: f32,
+    burst_done: bool,
+}
+
+impl ParticleEmitter {
+    pub fn new(preset: impl Into<String>) -> Self {
+        Self {
+            preset: preset.into(),
+            elapsed: 0.0,
+            accumulator: 0.0,
+            burst_done: false,
+        }
+    }
+}
+
+// despawned once the preset duration is over, only set on effect entities that own their emitter
+#[derive(Component)]
+pub struct OneShotEffect;
+
+#[derive(Component)]
+pub struct Particle {
+    pub velocity: Vec2,
+    pub age: f32,
+    pub lifetime: f32,
+    pub preset: String,
+}
+
+/// Spawns a stand-alone effect at `transform`, it removes itself when its preset has a duration.
+pub fn spawn_effect(commands: &mut Commands, preset: &str, transform: Transform) -> Entity {
+    commands
+        .spawn((ParticleEmitter::new(preset), OneShotEffect, transform))
+        .id()
+}
+
+pub fn setup_particles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.insert_resource(EffectPresetsHandle(asset_server.load("effects.fx.toml")));
+    commands.insert_resource(SoftDotImage(images.add(soft_dot(32))));
+    commands.insert_resource(ParticleQuad(meshes.add(Rectangle::new(1.0, 1.0))));
+}
+
+/// White radial falloff used for particles and glows that don't name a texture.
+pub fn soft_dot(size: u32) -> Image {
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    let centre = (size as f32 - 1.0) / 2.0;
+    for y in 0..size {
+        for x in 0..size {
+            let d = Vec2::new(x as f32 - centre, y as f32 - centre).length() / centre;
+            let alpha = (1.0 - d).clamp(0.0, 1.0).powf(1.5);
+            data.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+// additive particles get `intensity` from their material
+fn particle_color(preset: &EmitterPreset, t: f32) -> Color {
+    let [_, r, g, b, a] = preset.color.sample(t);
+    Color::linear_rgba(r, g, b, a)
+}
+
+/// Scale for a particle of `size` pixels, sprites are sized by `custom_size` and the additive
+/// quad by its transform.
+fn particle_scale(preset: &EmitterPreset, size: f32) -> Vec3 {
+    if preset.additive {
+        Vec3::new(size, size, 1.0)
+    } else {
+        Vec3::ONE
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_emitters(
+    mut commands: Commands,
+    mut q_emitter: Query<
+        (
+            Entity,
+            &mut ParticleEmitter,
+            &GlobalTransform,
+            Has<OneShotEffect>,
+        ),
+        Without<Pooled>,
+    >,
+    presets_handle: Res<EffectPresetsHandle>,
+    presets: Res<Assets<EffectPresets>>,
+    soft_dot: Res<SoftDotImage>,
+    quad: Res<ParticleQuad>,
+    mut shared_materials: ResMut<ParticleMaterials>,
+    mut materials: ResMut<Assets<ParticleMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut pool: ResMut<EntityPool<Particle>>,
+    mut game_rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
+    let Some(presets) = presets.get(&presets_handle.0) else {
+        return;
+    };
+    let rng = game_rng.stream(RngStream::Particles);
+    let dt = time.delta_secs();
+
+    for (entity, mut emitter, global_transform, one_shot) in q_emitter.iter_mut() {
+        let Some(preset) = presets.emitters.get(&emitter.preset) else {
+            continue;
+        };
+
+        let mut count = 0;
+        if !emitter.burst_done {
+            emitter.burst_done = true;
+            count += preset.burst;
+        }
+        emitter.accumulator += preset.rate * dt;
+        count += emitter.accumulator as u32;
+        emitter.accumulator = emitter.accumulator.fract();
+
+        let (_, rotation, origin) = global_transform.to_scale_rotation_translation();
+        let heading = rotation.to_euler(EulerRot::XYZ).2 + preset.direction.to_radians();
+        let half_cone = preset.cone.to_radians() / 2.0;
+        let image = match &preset.texture {
+            Some(path) => asset_server.load(path.clone()),
+            None => soft_dot.0.clone(),
+        };
+        let material = preset
+            .additive
+            .then(|| shared_materials.get(&mut materials, &image, preset.intensity));
+
+        for _ in 0..count {
+            let angle = heading + rng.random_range(-half_cone..=half_cone);
+            let speed = rng.random_range(preset.speed[0]..=preset.speed[1]);
+            let lifetime = rng.random_range(preset.lifetime[0]..=preset.lifetime[1]);
+            let size = preset.size.sample(0.0)[1];
+            let mut particle = pool.acquire(&mut commands);
+            particle.insert((
+                Transform::from_xyz(origin.x, origin.y, preset.z)
+                    .with_scale(particle_scale(preset, size)),
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    age: 0.0,
+                    lifetime,
+                    preset: emitter.preset.clone(),
+                },
+            ));
+            // a pooled particle may come back as the other kind
+            if let Some(material) = &material {
+                particle.remove::<Sprite>().insert((
+                    Mesh2d(quad.0.clone()),
+                    MeshMaterial2d(material.clone()),
+                    particle_tint(particle_color(preset, 0.0)),
+                ));
+            } else {
+                particle
+                    .remove::<(Mesh2d, MeshMaterial2d<ParticleMaterial>, MeshTag)>()
+                    .insert(Sprite {
+                        image: image.clone(),
+                        color: particle_color(preset, 0.0),
+                        custom_size: Some(Vec2::splat(size)),
+                        ..default()
+                    });
+            }
+        }
+
+        emitter.elapsed += dt;
+        if one_shot && preset.duration.is_some_and(|d| emitter.elapsed >= d) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_particles(
+    mut commands: Commands,
+    mut q_particle: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        Option<&mut Sprite>,
+        Option<&mut MeshTag>,
+    )>,
+    presets_handle: Res<EffectPresetsHandle>,
+    presets: Res<Assets<EffectPresets>>,
+    mut pool: ResMut<EntityPool<Particle>>,
+    time: Res<Time>,
+) {
+    let Some(presets) = presets.get(&presets_handle.0) else {
+        return;
+    };
+    let dt = time.delta_secs();
+
+    for (entity, mut particle, mut transform, sprite, tint) in q_particle.iter_mut() {
+        particle.age += dt;
+        let Some(preset) = presets.emitters.get(&particle.preset) else {
+            pool.release(&mut commands, entity);
+            continue;
+        };
+        if particle.age >= particle.lifetime {
+            pool.release(&mut commands, entity);
+            continue;
+        }
+
+        let t = particle.age / particle.lifetime;
+        particle.velocity *= (1.0 - preset.drag * dt).max(0.0);
+        transform.translation += (particle.velocity * dt).extend(0.0);
+        let size = preset.size.sample(t)[1];
+        let color = particle_color(preset, t);
+        transform.scale = particle_scale(preset, size);
+        if let Some(mut sprite) = sprite {
+            sprite.custom_size = Some(Vec2::splat(size));
+            sprite.color = color;
+        }
+        if let Some(mut tint) = tint {
+            *tint = particle_tint(color);
+        }
+    }
+}
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(Material2dPlugin::<ParticleMaterial>::default())
+            .init_asset::<EffectPresets>()
+            .init_asset_loader::<crate::asset_loader::EffectPresetsLoader>()
+            .init_resource::<EntityPool<Particle>>()
+            .init_resource::<ParticleMaterials>()
+            .add_systems(Startup, setup_particles)
+            .add_systems(
+                Update,
+                (run_emitters, update_particles)
+                    .chain()
+                    .in_set(GameSet::Presentation),
+            );
+    }
+}
@@ -0,0 +1,523 @@
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use bevy::window::{AppLifecycle, WindowFocused};
+use bevy_rapier2d::prelude::RapierConfiguration;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::components_and_resources::ConfigHandle;
+use crate::config::{Config, GraphicsQuality, Settings};
+use crate::replay::LiveInput;
+use crate::rng;
+
+// Escape or P pauses the game: virtual time stops, so no fixed ticks run, physics is switched
+// off and sounds hold. The game also pauses when the window loses focus or the app is sent to
+// the background, nobody wants to come back to a dead station.
+//
+// The menu is also where a seed is typed in, with the keyboard or the on-screen digits, to play
+// someone else's run. Restarting and playing a seed relaunch the game, a recorded game records
+// the new run to a new file next to the old one.
+
+const SCREEN_SHAKE_STEPS: [f32; 3] = [0.0, 0.5, 1.0];
+const BUTTON_COLOR: Color = Color::srgba(0.15, 0.15, 0.25, 0.9);
+const BUTTON_HOVER_COLOR: Color = Color::srgba(0.3, 0.3, 0.5, 0.9);
+const BUTTON_WIDTH: f32 = 380.0;
+const DIGIT_BUTTON_WIDTH: f32 = 64.0;
+// every 19 digit number fits in a u64
+const MAX_SEED_DIGITS: usize = 19;
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+/// Which page of the pause menu is showing.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PausePage {
+    #[default]
+    Main,
+    Settings,
+    Seed,
+}
+
+/// The seed being typed on the seed page, and what happened to the last one played.
+#[derive(Resource, Debug, Default)]
+pub struct SeedEntry {
+    pub digits: String,
+    pub note: Option<String>,
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseButton {
+    Resume,
+    Settings,
+    Restart,
+    Quit,
+    CameraEffects,
+    ScreenShake,
+    HitStop,
+    Quality,
+    EnterSeed,
+    Digit(u8),
+    DeleteDigit,
+    PlaySeed,
+    Back,
+}
+
+#[derive(Component)]
+pub struct PauseMenu;
+
+pub fn toggle_pause(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<PauseState>>,
+    mut next_state: ResMut<NextState<PauseState>>,
+) {
+    if keyboard_input.any_just_pressed([KeyCode::Escape, KeyCode::KeyP]) {
+        next_state.set(match state.get() {
+            PauseState::Running => PauseState::Paused,
+            PauseState::Paused => PauseState::Running,
+        });
+    }
+}
+
+pub fn pause_when_away(
+    mut focus_events: EventReader<WindowFocused>,
+    mut lifecycle_events: EventReader<AppLifecycle>,
+    mut next_state: ResMut<NextState<PauseState>>,
+) {
+    let lost_focus = focus_events.read().any(|event| !event.focused);
+    let backgrounded = lifecycle_events
+        .read()
+        .any(|event| matches!(event, AppLifecycle::WillSuspend | AppLifecycle::Suspended));
+    if lost_focus || backgrounded {
+        next_state.set(PauseState::Paused);
+    }
+}
+
+pub fn freeze(
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut q_physics: Query<&mut RapierConfiguration>,
+    q_audio: Query<&AudioSink>,
+    mut page: ResMut<PausePage>,
+) {
+    virtual_time.pause();
+    for mut physics in q_physics.iter_mut() {
+        physics.physics_pipeline_active = false;
+    }
+    for sink in q_audio.iter() {
+        sink.pause();
+    }
+    *page = PausePage::Main;
+}
+
+pub fn unfreeze(
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut q_physics: Query<&mut RapierConfiguration>,
+    q_audio: Query<&AudioSink>,
+    mut live_input: ResMut<LiveInput>,
+) {
+    virtual_time.unpause();
+    for mut physics in q_physics.iter_mut() {
+        physics.physics_pipeline_active = true;
+    }
+    for sink in q_audio.iter() {
+        sink.play();
+    }
+    // clicks on the menu aren't shots
+    *live_input = LiveInput::default();
+}
+
+fn button_label(button: PauseButton, config: Option<&Config>) -> String {
+    let settings = config.map(|config| &config.settings);
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    match button {
+        PauseButton::Resume => "Resume".to_string(),
+        PauseButton::Settings => "Settings".to_string(),
+        PauseButton::Restart => "Restart".to_string(),
+        PauseButton::Quit => "Quit".to_string(),
+        PauseButton::CameraEffects => format!(
+            "Camera effects: {}",
+            on_off(settings.is_none_or(|s| s.camera_effects))
+        ),
+        PauseButton::ScreenShake => format!(
+            "Screen shake: {:.0}%",
+            settings.map_or(1.0, |s| s.screen_shake) * 100.0
+        ),
+        PauseButton::HitStop => {
+            format!("Hit-stop: {}", on_off(settings.is_none_or(|s| s.hit_stop)))
+        }
+        PauseButton::Quality => format!(
+            "Quality: {}",
+            match settings.map_or(GraphicsQuality::High, |s| s.quality) {
+                GraphicsQuality::Low => "low",
+                GraphicsQuality::High => "high",
+            }
+        ),
+        PauseButton::EnterSeed => "Play a seed".to_string(),
+        PauseButton::Digit(digit) => digit.to_string(),
+        PauseButton::DeleteDigit => "Delete".to_string(),
+        PauseButton::PlaySeed => "Play".to_string(),
+        PauseButton::Back => "Back".to_string(),
+    }
+}
+
+fn page_buttons(page: PausePage) -> Vec<PauseButton> {
+    match page {
+        PausePage::Main => {
+            let mut buttons = vec![
+                PauseButton::Resume,
+                PauseButton::Settings,
+                PauseButton::EnterSeed,
+            ];
+            // a restart starts the game over as a new process, android apps can't do that
+            if cfg!(not(target_os = "android")) {
+                buttons.push(PauseButton::Restart);
+            }
+            buttons.push(PauseButton::Quit);
+            buttons
+        }
+        PausePage::Settings => vec![
+            PauseButton::CameraEffects,
+            PauseButton::ScreenShake,
+            PauseButton::HitStop,
+            PauseButton::Quality,
+            PauseButton::Back,
+        ],
+        PausePage::Seed => (1..=9)
+            .chain([0])
+            .map(PauseButton::Digit)
+            .chain([
+                PauseButton::DeleteDigit,
+                PauseButton::PlaySeed,
+                PauseButton::Back,
+            ])
+            .collect(),
+    }
+}
+
+fn spawn_button(parent: &mut ChildSpawnerCommands, button: PauseButton, label: String) {
+    let width = match button {
+        PauseButton::Digit(_) => DIGIT_BUTTON_WIDTH,
+        _ => BUTTON_WIDTH,
+    };
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(width),
+                padding: UiRect::all(Val::Px(12.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            button,
+        ))
+        .with_child((
+            Text::new(label),
+            TextFont {
+                font_size: 32.0,
+                ..default()
+            },
+        ));
+}
+
+/// Builds the menu for the current page, again whenever the page, the settings or the seed being
+/// typed change.
+pub fn draw_pause_menu(
+    mut commands: Commands,
+    page: Res<PausePage>,
+    entry: Res<SeedEntry>,
+    config_handle: Option<Res<ConfigHandle>>,
+    configs: Res<Assets<Config>>,
+    mut config_events: EventReader<AssetEvent<Config>>,
+    q_menu: Query<Entity, With<PauseMenu>>,
+) {
+    let config_changed = config_events.read().count() > 0;
+    let menu = q_menu.single();
+    if menu.is_ok() && !page.is_changed() && !entry.is_changed() && !config_changed {
+        return;
+    }
+    if let Ok(menu) = menu {
+        commands.entity(menu).despawn();
+    }
+    let config = config_handle.and_then(|handle| configs.get(&handle.0));
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            GlobalZIndex(20),
+            StateScoped(PauseState::Paused),
+            PauseMenu,
+        ))
+        .with_children(|menu| {
+            let title = match *page {
+                PausePage::Main => "Paused",
+                PausePage::Settings => "Settings",
+                PausePage::Seed => "Play a seed",
+            };
+            menu.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 72.0,
+                    ..default()
+                },
+            ));
+            if *page == PausePage::Seed {
+                let typed = match entry.digits.as_str() {
+                    "" => "type or tap a seed",
+                    digits => digits,
+                };
+                for (text, font_size) in
+                    [(typed, 45.0), (entry.note.as_deref().unwrap_or(""), 24.0)]
+                {
+                    menu.spawn((
+                        Text::new(text),
+                        TextFont {
+                            font_size,
+                            ..default()
+                        },
+                    ));
+                }
+            }
+            let (digits, buttons): (Vec<_>, Vec<_>) = page_buttons(*page)
+                .into_iter()
+                .partition(|button| matches!(button, PauseButton::Digit(_)));
+            if !digits.is_empty() {
+                menu.spawn(Node {
+                    width: Val::Px(BUTTON_WIDTH),
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.0),
+                    column_gap: Val::Px(8.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    for button in digits {
+                        spawn_button(row, button, button_label(button, config));
+                    }
+                });
+            }
+            for button in buttons {
+                spawn_button(menu, button, button_label(button, config));
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn highlight_pause_buttons(
+    mut q_button: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<PauseButton>, Changed<Interaction>),
+    >,
+) {
+    for (interaction, mut color) in q_button.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => BUTTON_HOVER_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        };
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn press_pause_buttons(
+    q_button: Query<(&Interaction, &PauseButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<PauseState>>,
+    mut page: ResMut<PausePage>,
+    mut entry: ResMut<SeedEntry>,
+    config_handle: Option<Res<ConfigHandle>>,
+    mut configs: ResMut<Assets<Config>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for (interaction, button) in q_button.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            PauseButton::Resume => next_state.set(PauseState::Running),
+            PauseButton::Settings => *page = PausePage::Settings,
+            PauseButton::Back => *page = PausePage::Main,
+            PauseButton::EnterSeed => {
+                *page = PausePage::Seed;
+                *entry = SeedEntry::default();
+            }
+            PauseButton::Digit(digit) => type_digit(&mut entry, char::from(b'0' + digit)),
+            PauseButton::DeleteDigit => {
+                entry.digits.pop();
+            }
+            PauseButton::PlaySeed => play_seed(&mut entry, &mut exit),
+            PauseButton::Restart => relaunch(std::env::args_os().skip(1).collect(), &mut exit),
+            PauseButton::Quit => {
+                exit.write(AppExit::Success);
+            }
+            _ => {
+                // settings only change for this session, config.toml is left alone
+                if let Some(config) = config_handle
+                    .as_ref()
+                    .and_then(|handle| configs.get_mut(&handle.0))
+                {
+                    change_setting(*button, &mut config.settings);
+                }
+            }
+        }
+    }
+}
+
+fn change_setting(button: PauseButton, settings: &mut Settings) {
+    match button {
+        PauseButton::CameraEffects => settings.camera_effects = !settings.camera_effects,
+        PauseButton::ScreenShake => {
+            settings.screen_shake = SCREEN_SHAKE_STEPS
+                .into_iter()
+                .find(|step| *step > settings.screen_shake + f32::EPSILON)
+                .unwrap_or(SCREEN_SHAKE_STEPS[0]);
+        }
+        PauseButton::HitStop => settings.hit_stop = !settings.hit_stop,
+        PauseButton::Quality => {
+            settings.quality = match settings.quality {
+                GraphicsQuality::Low => GraphicsQuality::High,
+                GraphicsQuality::High => GraphicsQuality::Low,
+            };
+        }
+        _ => {}
+    }
+}
+
+/// Typing on the seed page, digits, Backspace and Enter.
+pub fn type_seed(
+    mut keyboard_events: EventReader<KeyboardInput>,
+    page: Res<PausePage>,
+    mut entry: ResMut<SeedEntry>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for event in keyboard_events.read() {
+        if *page != PausePage::Seed || event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(text) => {
+                for digit in text.chars().filter(char::is_ascii_digit) {
+                    type_digit(&mut entry, digit);
+                }
+            }
+            Key::Backspace => {
+                entry.digits.pop();
+            }
+            Key::Enter => play_seed(&mut entry, &mut exit),
+            _ => {}
+        }
+    }
+}
+
+fn type_digit(entry: &mut SeedEntry, digit: char) {
+    if entry.digits.len() < MAX_SEED_DIGITS {
+        entry.digits.push(digit);
+        entry.note = None;
+    }
+}
+
+/// Starts a new run on the typed seed. Where the game can't relaunch itself the seed is kept for
+/// the next time it is opened.
+fn play_seed(entry: &mut SeedEntry, exit: &mut EventWriter<AppExit>) {
+    let Ok(seed) = entry.digits.parse::<u64>() else {
+        entry.note = Some("type a seed first".to_string());
+        return;
+    };
+    if rng::save_next_seed(seed) {
+        entry.note = Some(format!("seed {seed} starts next time the game is opened"));
+        return;
+    }
+    // the new seed replaces any seed, daily or replay the game was started with
+    let mut args: Vec<OsString> = Vec::new();
+    let mut old_args = std::env::args_os().skip(1);
+    while let Some(arg) = old_args.next() {
+        match arg.to_str() {
+            Some("--seed" | "--replay") => {
+                old_args.next();
+            }
+            Some("--daily") => {}
+            _ => args.push(arg),
+        }
+    }
+    args.extend(["--seed".into(), seed.to_string().into()]);
+    relaunch(args, exit);
+}
+
+/// Starts a fresh copy of the game with `args` and shuts this one down properly, so the run's
+/// replay is still saved.
+fn relaunch(args: Vec<OsString>, exit: &mut EventWriter<AppExit>) {
+    let args = record_to_new_file(args);
+    let started =
+        std::env::current_exe().and_then(|exe| std::process::Command::new(exe).args(args).spawn());
+    match started {
+        Ok(_) => {
+            exit.write(AppExit::Success);
+        }
+        Err(err) => warn!("can't restart the game: {err}"),
+    }
+}
+
+/// Points `--record` at a file that doesn't exist yet, run.replay becomes run-1.replay and so on,
+/// so the new run doesn't write over the one this process saves as it exits.
+pub fn record_to_new_file(args: Vec<OsString>) -> Vec<OsString> {
+    let mut args = args.into_iter();
+    let mut out = Vec::new();
+    while let Some(arg) = args.next() {
+        let record = arg == "--record";
+        out.push(arg);
+        if record && let Some(path) = args.next() {
+            out.push(unused_path(Path::new(&path)).into());
+        }
+    }
+    out
+}
+
+fn unused_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{stem}-{n}{extension}")))
+        .find(|candidate| !candidate.exists())
+        .expect("some numbered file name is free")
+}
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<PauseState>()
+            .enable_state_scoped_entities::<PauseState>()
+            .init_resource::<PausePage>()
+            .init_resource::<SeedEntry>()
+            .add_systems(OnEnter(PauseState::Paused), freeze)
+            .add_systems(OnExit(PauseState::Paused), unfreeze)
+            .add_systems(Update, (toggle_pause, pause_when_away))
+            .add_systems(
+                Update,
+                // presses first, a redraw replaces the buttons
+                (
+                    type_seed,
+                    press_pause_buttons,
+                    draw_pause_menu,
+                    highlight_pause_buttons,
+                )
+                    .chain()
+                    .run_if(in_state(PauseState::Paused)),
+            );
+    }
+}
@@ -2,7 +2,68 @@ use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use bevy_rapier2d::prelude::*;
 
-use crate::components_and_resources::{Accuracy, AnimationConfig, Bullet, BulletFireSound, Player};
+use crate::animation::{
+    self, AnimationEvent, AnimationLibrary, AnimationLibraryHandle, SpriteAnimation,
+};
+use crate::collision::{self, Layer};
+use crate::components_and_resources::{Accuracy, Bullet, BulletFireSound, Player};
+use crate::console::{self, AddConsoleCommand, ConsoleReply};
+use crate::damage::{
+    DamageDealer, DamageKind, DeathHook, Health, Invincible, Invulnerability, OnDeath,
+};
+use crate::lighting::{self, Light2d};
+use crate::particles::{self, ParticleEmitter};
+use crate::pool::EntityPool;
+use crate::replay::PlayerInput;
+use crate::schedule::{GameSet, Interpolated};
+use crate::sprite_collider::SpriteCollider;
+
+const BULLET_LIFETIME: f32 = 3.0;
+const BULLET_RANGE: f32 = 2500.0;
+// how far past the window edges a bullet may travel before it counts as off-screen
+const ARENA_MARGIN: f32 = 200.0;
+// distance from the turret's centre to the end of its barrel
+const MUZZLE_OFFSET: f32 = 100.0;
+pub const TURRET_IMAGE: &str = "turret_5.png";
+pub const BULLET_IMAGE: &str = "bullet.png";
+const PLAYER_HEALTH: f32 = 100.0;
+// seconds the station shrugs off further hits after being hit
+const PLAYER_INVULNERABILITY: f32 = 0.5;
+
+/// The turret's gun, swapped with the console's `give`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Weapon {
+    pub name: &'static str,
+    /// Bullets per shot, fanned out evenly over `spread` radians.
+    pub bullets: u32,
+    pub spread: f32,
+    pub damage: f32,
+    pub speed: f32,
+}
+
+pub const WEAPONS: [Weapon; 3] = [
+    Weapon {
+        name: "cannon",
+        bullets: 1,
+        spread: 0.0,
+        damage: 50.0,
+        speed: 400.0,
+    },
+    Weapon {
+        name: "scatter",
+        bullets: 3,
+        spread: 0.35,
+        damage: 30.0,
+        speed: 400.0,
+    },
+    Weapon {
+        name: "rail",
+        bullets: 1,
+        spread: 0.0,
+        damage: 150.0,
+        speed: 900.0,
+    },
+];
 
 pub struct PlayerPlugin;
 
@@ -13,22 +74,10 @@ impl PlayerPlugin {
         meshes: ResMut<Assets<Mesh>>,
         materials: ResMut<Assets<ColorMaterial>>,
         asset_server: Res<AssetServer>,
-        //mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     ) {
-        //let texture = asset_server.load("turret2_fire_animation.png");
-        //let layout = TextureAtlasLayout::from_grid(UVec2::new(256, 256), 3, 2, None, None);
-        //DynamicSceneBuildert texture_atlas_layout = texture_atlas_layouts.add(layout);
-        let texture = asset_server.load("turret_5.png");
-        let anim_config = AnimationConfig::new(0, 5, 60, String::from("once"));
+        let texture = asset_server.load(TURRET_IMAGE);
 
         commands.spawn((
-            //Sprite::from_atlas_image(
-            //    texture,
-            //    TextureAtlas {
-            //        layout: texture_atlas_layout,
-            //        index: anim_config.first_sprite_index,
-            //    },
-            //),
             Sprite::from(texture),
             Transform::from_scale(Vec3::splat(0.075)),
             Player {
@@ -39,10 +88,15 @@ impl PlayerPlugin {
                 friction: 5.0,
                 fire_delay: Timer::from_seconds(0.2, TimerMode::Once),
             },
-            anim_config,
+            WEAPONS[0],
+            Health::new(PLAYER_HEALTH),
+            Invulnerability::new(PLAYER_INVULNERABILITY),
+            OnDeath(vec![DeathHook::Effect("station_sparks".to_string())]),
             RigidBody::KinematicPositionBased,
-            Collider::ball(100.0),
-            //Sensor,
+            Interpolated::default(),
+            SpriteCollider,
+            Layer::Player.groups(),
+            ActiveEvents::COLLISION_EVENTS,
         ));
     }
 
@@ -54,7 +108,9 @@ impl PlayerPlugin {
         q_window: Query<&Window, With<PrimaryWindow>>,
     ) {
         let time_step = time.delta_secs();
-        let win = q_window.single().unwrap();
+        let Ok(win) = q_window.single() else {
+            return;
+        };
         for (mut transform, mut player) in query.iter_mut() {
             let mut input_direction = Vec3::ZERO;
 
@@ -116,106 +172,161 @@ impl PlayerPlugin {
     }
 
     pub fn player_rotate(
-        q_window: Query<&Window, With<PrimaryWindow>>,
         mut q_player: Query<&mut Transform, With<Player>>,
+        input: Res<PlayerInput>,
     ) {
-        let win = match q_window.single() {
-            Ok(k) => k,
-            Err(_e) => return,
-        };
         let mut transform = match q_player.single_mut() {
             Ok(k) => k,
             Err(_e) => return,
         };
-        let position = match win.cursor_position() {
-            Some(k) => k,
-            None => return,
-        };
-        // rotation logic
-        // don't touch, ever.
-        let win_length = win.size().x;
-        let win_height = win.size().y;
-        let pos = Vec3::from((
-            position.x - win_length / 2.0,
-            win_height / 2.0 - position.y,
-            0.0,
-        ));
-        let mut dir = pos - transform.translation;
-        dir = dir.normalize();
-        let angle = dir.y.atan2(dir.x);
-        transform.rotation = Quat::from_rotation_z(angle);
+        transform.rotation = Quat::from_rotation_z(input.aim);
     }
 
-    #[allow(clippy::too_many_arguments)]
     pub fn fire_bullet(
         mut commands: Commands,
-        keyboard_input: Res<ButtonInput<KeyCode>>,
-        mouse_input: Res<ButtonInput<MouseButton>>,
-        meshes: ResMut<Assets<Mesh>>,
-        materials: ResMut<Assets<ColorMaterial>>,
+        input: Res<PlayerInput>,
         asset_server: Res<AssetServer>,
-        mut query: Query<&mut Transform, With<Player>>,
-        mut q_player: Query<&mut Player, With<Player>>,
-        q_windows: Query<&Window, With<PrimaryWindow>>,
+        query: Query<(&Transform, &Weapon, Has<SpriteAnimation>), With<Player>>,
         mut q_accuracy: Query<&mut Accuracy, With<Accuracy>>,
-        time: Res<Time>,
+        mut bullet_pool: ResMut<EntityPool<Bullet>>,
+        mut fire_sound_pool: ResMut<EntityPool<BulletFireSound>>,
     ) {
-        let player = q_player.single_mut();
-        if keyboard_input.just_pressed(KeyCode::Space)
-            || mouse_input.just_pressed(MouseButton::Left)
-        {
-            let win = q_windows.single().unwrap();
-            let position = win.cursor_position().unwrap();
-            let win_length = win.size().x;
-            let win_height = win.size().y;
-            for transform in query.iter_mut() {
-                let pos = Vec3::from((
-                    position.x - win_length / 2.0,
-                    win_height / 2.0 - position.y,
-                    0.0,
-                ));
-                let mut dir = pos - transform.translation;
-                dir = dir.normalize();
-                let angle = dir.y.atan2(dir.x);
-                let bullet = commands
-                    .spawn((
-                        Sprite::from_image(asset_server.load("bullet.png")),
+        if input.fire {
+            for (transform, weapon, animated) in query.iter() {
+                for index in 0..weapon.bullets {
+                    // fanned out evenly either side of the aim
+                    let offset = if weapon.bullets > 1 {
+                        weapon.spread * (index as f32 / (weapon.bullets - 1) as f32 - 0.5)
+                    } else {
+                        0.0
+                    };
+                    let angle = input.aim + offset;
+                    let dir = Vec2::from_angle(angle).extend(0.0);
+                    bullet_pool.acquire(&mut commands).insert((
+                        Sprite {
+                            image: asset_server.load(BULLET_IMAGE),
+                            color: lighting::BULLET_EMISSIVE,
+                            ..default()
+                        },
                         Transform::from_translation(transform.translation)
                             .with_scale(Vec3::splat(0.2))
                             .with_rotation(Quat::from_rotation_z(angle)),
                         Bullet {
-                            speed: 400.0,
+                            speed: weapon.speed,
                             direction: dir,
+                            lifetime: Timer::from_seconds(BULLET_LIFETIME, TimerMode::Once),
+                            range: BULLET_RANGE,
+                            travelled: 0.0,
+                            script: None,
                         },
                         RigidBody::KinematicPositionBased,
-                        Collider::ball(100.0),
-                        // Sensor,
-                    ))
-                    .id();
-                let bullet_fire_entity = commands
-                    .spawn((
-                        AudioPlayer::new(asset_server.load("fire.ogg")),
-                        PlaybackSettings::ONCE,
-                        BulletFireSound {
-                            duration: Timer::from_seconds(2.0, TimerMode::Once),
+                        Interpolated::default(),
+                        SpriteCollider,
+                        collision::projectile_physics(Layer::PlayerProjectile),
+                        ParticleEmitter::new("bullet_trail"),
+                        DamageDealer {
+                            amount: weapon.damage,
+                            kind: DamageKind::Kinetic,
+                        },
+                        Light2d {
+                            color: Color::srgb(0.5, 0.7, 1.0),
+                            radius: 60.0,
+                            intensity: 1.5,
                         },
-                    ))
-                    .id();
-                let mut accuracy = q_accuracy.single_mut().unwrap();
-                accuracy.bullets_fired += 1.0;
+                    ));
+                    if let Ok(mut accuracy) = q_accuracy.single_mut() {
+                        accuracy.bullets_fired += 1.0;
+                    }
+                }
+                // a fire clip flashes on its muzzle_flash frame instead
+                if !animated {
+                    Self::spawn_muzzle_flash(&mut commands, transform);
+                }
+                fire_sound_pool.acquire(&mut commands).insert((
+                    AudioPlayer::new(asset_server.load("fire.ogg")),
+                    PlaybackSettings::ONCE,
+                    BulletFireSound {
+                        duration: Timer::from_seconds(2.0, TimerMode::Once),
+                    },
+                ));
             }
         }
     }
 
+    fn spawn_muzzle_flash(commands: &mut Commands, transform: &Transform) {
+        let muzzle = transform.translation + transform.rotation * Vec3::X * MUZZLE_OFFSET;
+        particles::spawn_effect(
+            commands,
+            "muzzle_flash",
+            Transform::from_translation(muzzle).with_rotation(transform.rotation),
+        );
+        lighting::spawn_flash(
+            commands,
+            muzzle,
+            Light2d {
+                color: Color::srgb(1.0, 0.8, 0.4),
+                radius: 90.0,
+                intensity: 2.0,
+            },
+            0.08,
+        );
+    }
+
+    pub fn flash_muzzle_on_clip_event(
+        mut commands: Commands,
+        mut events: EventReader<AnimationEvent>,
+        q_player: Query<&Transform, With<Player>>,
+    ) {
+        for event in events.read().filter(|event| event.name == "muzzle_flash") {
+            if let Ok(transform) = q_player.get(event.entity) {
+                Self::spawn_muzzle_flash(&mut commands, transform);
+            }
+        }
+    }
+
+    pub fn toggle_god_mode(
+        In(_): In<Vec<String>>,
+        mut commands: Commands,
+        q_player: Query<(Entity, Has<Invincible>), With<Player>>,
+    ) -> ConsoleReply {
+        let Ok((player, invincible)) = q_player.single() else {
+            return Err("there is no player".to_string());
+        };
+        if invincible {
+            commands.entity(player).remove::<Invincible>();
+            Ok("god mode off".to_string())
+        } else {
+            commands.entity(player).insert(Invincible);
+            Ok("god mode on".to_string())
+        }
+    }
+
+    pub fn give_weapon(
+        In(args): In<Vec<String>>,
+        mut q_weapon: Query<&mut Weapon, With<Player>>,
+    ) -> ConsoleReply {
+        let name: String = console::arg(&args, 0, "weapon")?;
+        let Some(weapon) = WEAPONS.iter().find(|weapon| weapon.name == name) else {
+            let names: Vec<_> = WEAPONS.iter().map(|weapon| weapon.name).collect();
+            return Err(format!("no weapon {name}, there is {}", names.join(", ")));
+        };
+        let Ok(mut current) = q_weapon.single_mut() else {
+            return Err("there is no player".to_string());
+        };
+        *current = *weapon;
+        Ok(format!("gave the {name}"))
+    }
+
     pub fn remove_bullet_sound_entities(
         mut s_query: Query<(&mut BulletFireSound, Entity), With<AudioPlayer>>,
         mut commands: Commands,
+        mut pool: ResMut<EntityPool<BulletFireSound>>,
         time: Res<Time>,
     ) {
         for (mut fire_sound, entity) in s_query.iter_mut() {
             fire_sound.duration.tick(time.delta());
             if fire_sound.duration.just_finished() {
-                commands.entity(entity).despawn();
+                pool.release(&mut commands, entity);
             }
         }
     }
@@ -225,32 +336,76 @@ impl PlayerPlugin {
         time: Res<Time>,
     ) {
         let time_step = time.delta_secs();
-        for (mut transform, bullet) in query.iter_mut() {
+        for (mut transform, mut bullet) in query.iter_mut() {
+            let bullet = &mut *bullet;
+            if let Some(script) = &mut bullet.script {
+                script.age += time_step;
+                bullet.speed = script.speed.sample(script.age)[1];
+                if script.turn != 0.0 {
+                    let turn = Quat::from_rotation_z(script.turn * time_step);
+                    bullet.direction = turn * bullet.direction;
+                    transform.rotate_z(script.turn * time_step);
+                }
+            }
             transform.translation += bullet.speed * time_step * bullet.direction.normalize();
+            bullet.travelled += bullet.speed * time_step;
         }
     }
 
-    pub fn execute_animations_player(
+    // bullets that missed everything go back to the pool once they are out of time, out of
+    // range or outside the arena, whichever comes first
+    pub fn retire_bullets(
+        mut query: Query<(Entity, &Transform, &mut Bullet)>,
+        q_window: Query<&Window, With<PrimaryWindow>>,
+        mut commands: Commands,
+        mut pool: ResMut<EntityPool<Bullet>>,
         time: Res<Time>,
-        mut query: Query<(&mut AnimationConfig, &mut Sprite), With<Player>>,
-        mouse_input: Res<ButtonInput<MouseButton>>,
     ) {
-        for (mut config, mut sprite) in &mut query {
-            if mouse_input.just_pressed(MouseButton::Left) {
-                config.frame_timer.reset();
+        let arena = q_window
+            .single()
+            .map(|win| Rect::from_center_size(Vec2::ZERO, win.size() + ARENA_MARGIN))
+            .ok();
+        for (entity, transform, mut bullet) in query.iter_mut() {
+            bullet.lifetime.tick(time.delta());
+            let outside_arena =
+                arena.is_some_and(|arena| !arena.contains(transform.translation.truncate()));
+            if bullet.lifetime.finished() || bullet.travelled >= bullet.range || outside_arena {
+                pool.release(&mut commands, entity);
             }
-            config.frame_timer.tick(time.delta());
+        }
+    }
 
-            if config.frame_timer.just_finished() {
-                if let Some(atlas) = &mut sprite.texture_atlas {
-                    if atlas.index == config.last_sprite_index - 1 {
-                        atlas.index = config.first_sprite_index;
-                    } else {
-                        atlas.index += 1;
-                        config.frame_timer =
-                            AnimationConfig::timer_from_fps(config.fps, String::from("once"));
-                    }
-                }
+    // the fire clip comes from clips.anim.toml, so it can only be attached once that is loaded
+    #[allow(clippy::type_complexity)]
+    pub fn bind_fire_animation(
+        mut commands: Commands,
+        mut q_player: Query<(Entity, &mut Transform), (With<Player>, Without<SpriteAnimation>)>,
+        library_handle: Res<AnimationLibraryHandle>,
+        libraries: Res<Assets<AnimationLibrary>>,
+    ) {
+        let Some(library) = libraries.get(&library_handle.0) else {
+            return;
+        };
+        let Some(fire_clip) = &library.player.fire_clip else {
+            return;
+        };
+        for (entity, mut transform) in q_player.iter_mut() {
+            if let Some(scale) = library.player.scale {
+                transform.scale = Vec3::splat(scale);
+            }
+            commands
+                .entity(entity)
+                .insert(SpriteAnimation::new(fire_clip.clone()));
+        }
+    }
+
+    pub fn play_fire_animation(
+        mut query: Query<&mut SpriteAnimation, With<Player>>,
+        input: Res<PlayerInput>,
+    ) {
+        if input.fire {
+            for mut animation in query.iter_mut() {
+                animation.play();
             }
         }
     }
@@ -258,10 +413,27 @@ impl PlayerPlugin {
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, Self::setup_player)
-            .add_systems(Update, Self::player_rotate)
-            .add_systems(Update, Self::fire_bullet)
-            .add_systems(Update, Self::move_bullet)
-            .add_systems(Update, Self::execute_animations_player)
-            .add_systems(Update, Self::remove_bullet_sound_entities);
+            .add_systems(
+                FixedUpdate,
+                (
+                    Self::player_rotate,
+                    Self::fire_bullet.after(Self::player_rotate),
+                    Self::move_bullet,
+                    Self::retire_bullets.after(Self::move_bullet),
+                    Self::play_fire_animation,
+                )
+                    .in_set(GameSet::Simulation),
+            )
+            .add_systems(
+                Update,
+                (
+                    Self::bind_fire_animation,
+                    Self::flash_muzzle_on_clip_event.after(animation::advance_animations),
+                    Self::remove_bullet_sound_entities,
+                )
+                    .in_set(GameSet::Presentation),
+            )
+            .add_run_command("god", "god", Self::toggle_god_mode)
+            .add_run_command("give", "give <cannon|scatter|rail>", Self::give_weapon);
     }
 }
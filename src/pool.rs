@@ -0,0 +1,71 @@
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::marker::PhantomData;
+
+use crate::components_and_resources::{Bullet, BulletFireSound, HitSoundBulletMeteor, Smoke};
+
+/// Marks an entity that is parked in an [`EntityPool`] and waiting to be reused.
+#[derive(Component)]
+pub struct Pooled;
+
+/// Free list of recycled entities for the short-lived objects tagged with `T` (bullets, smoke,
+/// one-shot sounds). Released entities are hidden and taken out of the physics world instead of
+/// despawned, so rapid fire doesn't keep allocating and freeing entities.
+#[derive(Resource)]
+pub struct EntityPool<T> {
+    free: Vec<Entity>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for EntityPool<T> {
+    fn default() -> Self {
+        Self {
+            free: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Component> EntityPool<T> {
+    /// Hands out a parked entity, or a fresh one if the pool is empty. The caller inserts the
+    /// components for the new use, which overwrite whatever the entity had last time.
+    pub fn acquire<'a>(&mut self, commands: &'a mut Commands) -> EntityCommands<'a> {
+        match self.free.pop() {
+            Some(entity) => {
+                let mut entity_commands = commands.entity(entity);
+                entity_commands
+                    .remove::<(Pooled, ColliderDisabled, RigidBodyDisabled, AudioSink)>()
+                    .insert(Visibility::Inherited);
+                entity_commands
+            }
+            None => commands.spawn_empty(),
+        }
+    }
+
+    /// Parks `entity` for reuse. Releasing an entity that is already parked is a no-op, so two
+    /// systems retiring the same bullet in one frame can't hand it out twice.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        if self.free.contains(&entity) {
+            return;
+        }
+        self.free.push(entity);
+        commands.entity(entity).remove::<T>().insert((
+            Pooled,
+            Visibility::Hidden,
+            ColliderDisabled,
+            RigidBodyDisabled,
+        ));
+    }
+}
+
+pub struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntityPool<Bullet>>()
+            .init_resource::<EntityPool<Smoke>>()
+            .init_resource::<EntityPool<HitSoundBulletMeteor>>()
+            .init_resource::<EntityPool<BulletFireSound>>();
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::asset::{LoadState, UntypedAssetId};
+use bevy::prelude::*;
+use bevy_rapier2d::plugin::PhysicsSet;
+use std::path::PathBuf;
+
+use crate::animation::{AnimationLibrary, AnimationLibraryHandle, ClipAtlases};
+use crate::boss;
+use crate::bullet_pattern::{self, PatternLibraryHandle};
+use crate::components_and_resources::ConfigHandle;
+use crate::components_and_resources::Player;
+use crate::config::Config;
+use crate::player;
+use crate::schedule::GameSet;
+
+// a collider is outlined from its sprite's image, so a body spawned before its image is in
+// flies without one for however long the disk takes. Until the config, the bullet patterns and
+// every image a collider is built from have loaded, no tick runs the simulation or physics, so
+// loading times never change how a run plays out and a replay sees the same first tick as its
+// recording. A turret with a fire clip is outlined from the clip's atlas, so that waits for the
+// turret to be switched over to it as well.
+
+/// Where loading the simulation's assets is at.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Default)]
+pub enum Preload {
+    #[default]
+    Loading,
+    Ready,
+    /// An asset couldn't be loaded, the game runs without waiting for the rest.
+    Failed(String),
+}
+
+/// Keeps the art colliders are built from loaded for the whole run.
+#[derive(Resource)]
+pub struct PreloadedImages(#[allow(dead_code)] Vec<Handle<Image>>);
+
+#[allow(clippy::too_many_arguments)]
+pub fn preload_assets(
+    mut commands: Commands,
+    mut preload: ResMut<Preload>,
+    asset_server: Res<AssetServer>,
+    config_handle: Option<Res<ConfigHandle>>,
+    configs: Res<Assets<Config>>,
+    library_handle: Option<Res<PatternLibraryHandle>>,
+    clips_handle: Option<Res<AnimationLibraryHandle>>,
+    clips: Res<Assets<AnimationLibrary>>,
+    atlases: Res<ClipAtlases>,
+    q_turret: Query<&Sprite, With<Player>>,
+    loaded_images: Res<Assets<Image>>,
+    mut images: Local<Vec<Handle<Image>>>,
+) {
+    if images.is_empty()
+        && let Some(config) = config_handle
+            .as_ref()
+            .and_then(|handle| configs.get(&handle.0))
+    {
+        // the meteor art is set in config.toml
+        *images = [
+            player::TURRET_IMAGE,
+            player::BULLET_IMAGE,
+            bullet_pattern::ENEMY_BULLET_IMAGE,
+        ]
+        .into_iter()
+        .chain(boss::boss_images())
+        .map(PathBuf::from)
+        .chain([config.assets.meteor.clone()])
+        .map(|path| asset_server.load(path))
+        .collect();
+    }
+
+    let mut waiting_on: Vec<UntypedAssetId> =
+        images.iter().map(|image| image.id().untyped()).collect();
+    waiting_on.extend(config_handle.map(|handle| handle.0.id().untyped()));
+    waiting_on.extend(library_handle.map(|handle| handle.0.id().untyped()));
+    waiting_on.extend(clips_handle.as_ref().map(|handle| handle.0.id().untyped()));
+    // the images are only known once the config is in, so nothing is missing at 3 + images
+    let all_known = !images.is_empty() && waiting_on.len() == images.len() + 3;
+    let fire_clip = clips_handle
+        .and_then(|handle| clips.get(&handle.0))
+        .map(|clips| {
+            clips
+                .player
+                .fire_clip
+                .as_deref()
+                .filter(|clip| !atlases.has_failed(clip))
+        });
+    // folder clips pack their atlas at runtime, so the asset server never reports it loaded
+    let mut turret_art = Vec::new();
+    let turret_switched = match fire_clip {
+        None => false,
+        Some(None) => true,
+        Some(Some(_)) => {
+            turret_art.extend(
+                q_turret
+                    .iter()
+                    .filter(|sprite| sprite.texture_atlas.is_some())
+                    .map(|sprite| sprite.image.id()),
+            );
+            turret_art.len() == q_turret.iter().len()
+                && turret_art.iter().all(|id| loaded_images.contains(*id))
+        }
+    };
+    if let Some(LoadState::Failed(err)) = waiting_on
+        .iter()
+        .copied()
+        .chain(turret_art.iter().map(|id| id.untyped()))
+        .map(|id| asset_server.load_state(id))
+        .find(LoadState::is_failed)
+    {
+        warn!("can't preload the game's assets: {err}");
+        *preload = Preload::Failed(err.to_string());
+        return;
+    }
+    if all_known
+        && turret_switched
+        && waiting_on
+            .iter()
+            .all(|id| asset_server.is_loaded_with_dependencies(*id))
+    {
+        commands.insert_resource(PreloadedImages(std::mem::take(&mut *images)));
+        *preload = Preload::Ready;
+    }
+}
+
+/// False while the simulation's assets are still loading.
+pub fn assets_ready(preload: Res<Preload>) -> bool {
+    *preload != Preload::Loading
+}
+
+pub struct PreloadPlugin;
+
+impl Plugin for PreloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Preload>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    GameSet::Input,
+                    GameSet::Simulation,
+                    GameSet::Collision,
+                    PhysicsSet::SyncBackend,
+                    PhysicsSet::StepSimulation,
+                    PhysicsSet::Writeback,
+                )
+                    .run_if(assets_ready),
+            )
+            .add_systems(
+                Update,
+                preload_assets.run_if(resource_equals(Preload::Loading)),
+            );
+    }
+}
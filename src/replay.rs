@@ -0,0 +1,547 @@
+use anyhow::{Context, bail};
+use bevy::app::{RunFixedMainLoop, RunFixedMainLoopSystem};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::camera_fx::{self, CameraRig};
+use crate::components_and_resources::{ConfigHandle, GameSpeed, Player};
+use crate::config::{Config, Spawning};
+use crate::damage::DeathEvent;
+use crate::pause::PauseState;
+use crate::rng::{GameRng, SeedSource};
+use crate::schedule::GameSet;
+
+// a run is its seed plus what the player did on every fixed tick. The simulation only ever sees
+// PlayerInput, filled from the mouse and keyboard when playing and from a recording when
+// watching one, so playing the same inputs on the same seed gives the same run.
+//
+// `--record <file>` writes the run to <file> when it ends, `--replay <file>` watches it. The
+// viewer pauses with Space, changes speed with Up/Down and skips ahead 10 seconds with Right.
+// Seeking only goes forward, going back means watching from the start again.
+
+/// Fixed ticks per second, everything that decides how a run plays out steps at this rate.
+pub const TICK_RATE: f64 = 64.0;
+
+const MAGIC: &[u8; 4] = b"ADVR";
+const VERSION: u8 = 2;
+// magic, version, seed, arena width and height, frame count, spawning settings length. The
+// spawning settings follow as JSON, then the frames.
+const HEADER_LEN: usize = 4 + 1 + 8 + 4 + 4 + 4 + 4;
+const FRAME_LEN: usize = 3;
+
+const FIRE: u8 = 1;
+const UP: u8 = 1 << 1;
+const DOWN: u8 = 1 << 2;
+const LEFT: u8 = 1 << 3;
+const RIGHT: u8 = 1 << 4;
+
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+const SEEK_SECONDS: f64 = 10.0;
+const SEEK_SPEED: f32 = 20.0;
+// lets the fixed loop catch up on a whole second per frame while seeking
+const SEEK_MAX_DELTA: Duration = Duration::from_secs(1);
+// bevy's default
+const NORMAL_MAX_DELTA: Duration = Duration::from_millis(250);
+
+/// What the player does during the current fixed tick.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerInput {
+    /// Radians the turret points at.
+    pub aim: f32,
+    pub fire: bool,
+    /// WASD, -1.0 to 1.0 on each axis.
+    pub movement: Vec2,
+}
+
+/// One tick of input as stored in a recording: the aim angle in 1/65536ths of a turn and a
+/// button bitmask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputFrame {
+    aim: u16,
+    buttons: u8,
+}
+
+impl InputFrame {
+    pub fn from_input(input: &PlayerInput) -> Self {
+        let turns = input.aim.rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU;
+        let mut buttons = 0;
+        for (pressed, bit) in [
+            (input.fire, FIRE),
+            (input.movement.y > 0.0, UP),
+            (input.movement.y < 0.0, DOWN),
+            (input.movement.x < 0.0, LEFT),
+            (input.movement.x > 0.0, RIGHT),
+        ] {
+            if pressed {
+                buttons |= bit;
+            }
+        }
+        Self {
+            aim: (turns * 65536.0).round() as u32 as u16,
+            buttons,
+        }
+    }
+
+    pub fn input(self) -> PlayerInput {
+        let axis = |positive: u8, negative: u8| {
+            f32::from(self.buttons & positive != 0) - f32::from(self.buttons & negative != 0)
+        };
+        PlayerInput {
+            aim: self.aim as f32 / 65536.0 * std::f32::consts::TAU,
+            fire: self.buttons & FIRE != 0,
+            movement: Vec2::new(axis(RIGHT, LEFT), axis(UP, DOWN)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub seed: u64,
+    /// Window size the run was played at, spawns depend on it.
+    pub arena: Vec2,
+    /// The `[spawning]` settings the run was played with, they decide where meteors appear.
+    pub spawning: Option<Spawning>,
+    pub frames: Vec<InputFrame>,
+}
+
+impl Recording {
+    pub fn encode(&self) -> Vec<u8> {
+        let spawning = self
+            .spawning
+            .as_ref()
+            .and_then(|spawning| serde_json::to_vec(spawning).ok())
+            .unwrap_or_default();
+        let mut bytes =
+            Vec::with_capacity(HEADER_LEN + spawning.len() + self.frames.len() * FRAME_LEN);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.arena.x.to_le_bytes());
+        bytes.extend_from_slice(&self.arena.y.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(spawning.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&spawning);
+        for frame in self.frames.iter() {
+            bytes.extend_from_slice(&frame.aim.to_le_bytes());
+            bytes.push(frame.buttons);
+        }
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            bail!("not a replay file");
+        }
+        if bytes[4] != VERSION {
+            bail!("replay version {} is not supported", bytes[4]);
+        }
+        let u32_at = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let seed = u64::from_le_bytes(bytes[5..13].try_into().unwrap());
+        let arena = Vec2::new(f32::from_bits(u32_at(13)), f32::from_bits(u32_at(17)));
+        let count = u32_at(21) as usize;
+        let spawning_len = u32_at(25) as usize;
+        let Some((spawning, frames)) = bytes[HEADER_LEN..].split_at_checked(spawning_len) else {
+            bail!("replay is truncated, expected {spawning_len} bytes of spawning settings");
+        };
+        let spawning = match spawning_len {
+            0 => None,
+            _ => Some(serde_json::from_slice(spawning).context("reading the spawning settings")?),
+        };
+        if count.checked_mul(FRAME_LEN) != Some(frames.len()) {
+            bail!("replay is truncated, expected {count} frames");
+        }
+        let frames = frames
+            .chunks_exact(FRAME_LEN)
+            .map(|frame| InputFrame {
+                aim: u16::from_le_bytes([frame[0], frame[1]]),
+                buttons: frame[2],
+            })
+            .collect();
+        Ok(Self {
+            seed,
+            arena,
+            spawning,
+            frames,
+        })
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::decode(&bytes).with_context(|| format!("loading {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, self.encode()).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+/// Where this run's inputs come from and go to.
+#[derive(Resource)]
+pub enum ReplayMode {
+    Live,
+    Recording {
+        path: PathBuf,
+        recording: Recording,
+        saved: bool,
+    },
+    Playback(Playback),
+}
+
+pub struct Playback {
+    pub recording: Recording,
+    pub tick: usize,
+    pub speed: usize,
+    pub seek_to: Option<usize>,
+}
+
+impl ReplayMode {
+    /// `--record <file>` or `--replay <file>`, live play otherwise.
+    pub fn from_args() -> Self {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let (flag, path) = match arg.as_str() {
+                "--record" | "--replay" => (arg.clone(), args.next().map(PathBuf::from)),
+                _ => continue,
+            };
+            let Some(path) = path else {
+                warn!("{flag} needs a file name");
+                continue;
+            };
+            if flag == "--record" {
+                return ReplayMode::Recording {
+                    path,
+                    recording: Recording::default(),
+                    saved: false,
+                };
+            }
+            match Recording::load(&path) {
+                Ok(recording) => {
+                    return ReplayMode::Playback(Playback {
+                        recording,
+                        tick: 0,
+                        speed: NORMAL_SPEED,
+                        seek_to: None,
+                    });
+                }
+                Err(err) => warn!("can't watch replay: {err:#}"),
+            }
+        }
+        ReplayMode::Live
+    }
+}
+
+/// Mouse and keyboard input gathered between fixed ticks. Presses are held until a tick has
+/// seen them, so a click never falls between two ticks.
+#[derive(Resource, Default)]
+pub struct LiveInput {
+    aim: Option<f32>,
+    fire: bool,
+    movement: Vec2,
+}
+
+impl LiveInput {
+    /// Points the turret at `angle` radians on the next tick, for players that aren't a mouse.
+    pub fn aim(&mut self, angle: f32) {
+        self.aim = Some(angle);
+    }
+
+    pub fn press_fire(&mut self) {
+        self.fire = true;
+    }
+}
+
+#[derive(Component)]
+pub struct ReplayHud;
+
+pub fn gather_live_input(
+    mut live: ResMut<LiveInput>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_player: Query<&Transform, With<Player>>,
+    q_rig: Query<&CameraRig>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) || mouse_input.just_pressed(MouseButton::Left) {
+        live.fire = true;
+    }
+    let axis = |positive: KeyCode, negative: KeyCode| {
+        f32::from(keyboard_input.pressed(positive)) - f32::from(keyboard_input.pressed(negative))
+    };
+    live.movement = Vec2::new(
+        axis(KeyCode::KeyD, KeyCode::KeyA),
+        axis(KeyCode::KeyW, KeyCode::KeyS),
+    );
+
+    let (Ok(win), Ok(transform)) = (q_window.single(), q_player.single()) else {
+        return;
+    };
+    let Some(cursor) = win.cursor_position() else {
+        return;
+    };
+    let target = Vec3::new(
+        cursor.x - win.size().x / 2.0,
+        win.size().y / 2.0 - cursor.y,
+        0.0,
+    ) + camera_fx::camera_lead(&q_rig);
+    let direction = (target - transform.translation).truncate();
+    if direction != Vec2::ZERO {
+        live.aim = Some(direction.to_angle());
+    }
+}
+
+/// Fills PlayerInput for this tick, from the recording when watching one. Live input goes
+/// through the same quantisation a recording does, so what was played is what gets replayed.
+pub fn next_player_input(
+    mut mode: ResMut<ReplayMode>,
+    mut live: ResMut<LiveInput>,
+    mut input: ResMut<PlayerInput>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    config_handle: Option<Res<ConfigHandle>>,
+    configs: Res<Assets<Config>>,
+) {
+    if let ReplayMode::Playback(playback) = &mut *mode {
+        match playback.recording.frames.get(playback.tick) {
+            Some(frame) => *input = frame.input(),
+            None => input.fire = false,
+        }
+        playback.tick += 1;
+        return;
+    }
+
+    let frame = InputFrame::from_input(&PlayerInput {
+        aim: live.aim.unwrap_or(input.aim),
+        fire: live.fire,
+        movement: live.movement,
+    });
+    live.fire = false;
+    *input = frame.input();
+    if let ReplayMode::Recording { recording, .. } = &mut *mode {
+        if recording.frames.is_empty() {
+            if let Ok(win) = q_window.single() {
+                recording.arena = win.size();
+            }
+            recording.spawning = config_handle
+                .and_then(|handle| configs.get(&handle.0))
+                .map(|config| config.spawning.clone());
+        }
+        recording.frames.push(frame);
+    }
+}
+
+/// Plays the replay with the spawning settings it was recorded with, whatever config.toml says
+/// now. Runs on the first tick, when the config is in.
+pub fn use_recorded_spawning(
+    mode: Res<ReplayMode>,
+    config_handle: Option<Res<ConfigHandle>>,
+    mut configs: ResMut<Assets<Config>>,
+    mut done: Local<bool>,
+) {
+    if *done {
+        return;
+    }
+    *done = true;
+    let ReplayMode::Playback(playback) = &*mode else {
+        return;
+    };
+    let Some(recorded) = &playback.recording.spawning else {
+        return;
+    };
+    let Some(config) = config_handle.and_then(|handle| configs.get_mut(&handle.0)) else {
+        warn!("no config loaded, the replay's spawning settings can't be applied");
+        return;
+    };
+    if config.spawning != *recorded {
+        info!("config.toml's [spawning] differs from the replay's, using the replay's");
+        config.spawning = recorded.clone();
+    }
+}
+
+fn save_recording(mode: &mut ReplayMode, seed: u64) {
+    let ReplayMode::Recording {
+        path,
+        recording,
+        saved,
+    } = mode
+    else {
+        return;
+    };
+    if *saved {
+        return;
+    }
+    recording.seed = seed;
+    match recording.save(path) {
+        Ok(()) => info!(
+            "recorded {} ticks to {}",
+            recording.frames.len(),
+            path.display()
+        ),
+        Err(err) => warn!("can't save replay: {err:#}"),
+    }
+    *saved = true;
+}
+
+/// Writes the recording when the player dies or the game is closed.
+pub fn save_recording_on_end(
+    mut mode: ResMut<ReplayMode>,
+    mut deaths: EventReader<DeathEvent>,
+    mut exits: EventReader<AppExit>,
+    q_player: Query<(), With<Player>>,
+    game_rng: Res<GameRng>,
+) {
+    let player_died = deaths.read().any(|death| q_player.contains(death.entity));
+    if player_died || exits.read().next().is_some() {
+        save_recording(&mut mode, game_rng.seed());
+    }
+}
+
+pub fn check_replay_arena(mode: Res<ReplayMode>, q_window: Query<&Window, With<PrimaryWindow>>) {
+    let (ReplayMode::Playback(playback), Ok(win)) = (&*mode, q_window.single()) else {
+        return;
+    };
+    if playback.recording.arena != win.size() {
+        warn!(
+            "replay was recorded at {}, this window is {}, it will play out differently",
+            playback.recording.arena,
+            win.size()
+        );
+    }
+}
+
+pub fn control_playback(
+    mut mode: ResMut<ReplayMode>,
+    mut game_speed: ResMut<GameSpeed>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    let ReplayMode::Playback(playback) = &mut *mode else {
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        if virtual_time.is_paused() {
+            virtual_time.unpause();
+        } else {
+            virtual_time.pause();
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        playback.speed = (playback.speed + 1).min(SPEEDS.len() - 1);
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        playback.speed = playback.speed.saturating_sub(1);
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowRight) {
+        let from = playback.seek_to.unwrap_or(playback.tick);
+        let target = from + (SEEK_SECONDS * TICK_RATE) as usize;
+        playback.seek_to = Some(target.min(playback.recording.frames.len()));
+        virtual_time.unpause();
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+        info!("replays can only seek forward, restart the replay to watch from the start");
+    }
+
+    if playback
+        .seek_to
+        .is_some_and(|target| playback.tick >= target)
+    {
+        playback.seek_to = None;
+    }
+    if playback.seek_to.is_some() {
+        game_speed.0 = SEEK_SPEED;
+        virtual_time.set_max_delta(SEEK_MAX_DELTA);
+    } else {
+        game_speed.0 = SPEEDS[playback.speed];
+        virtual_time.set_max_delta(NORMAL_MAX_DELTA);
+    }
+    if playback.tick >= playback.recording.frames.len() && !virtual_time.is_paused() {
+        info!("replay finished");
+        virtual_time.pause();
+    }
+}
+
+pub fn spawn_replay_hud(mut commands: Commands, mode: Res<ReplayMode>) {
+    if !matches!(*mode, ReplayMode::Playback(_)) {
+        return;
+    }
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 28.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            right: Val::Px(12.0),
+            ..default()
+        },
+        ReplayHud,
+    ));
+}
+
+fn clock(tick: usize) -> String {
+    let seconds = (tick as f64 / TICK_RATE) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub fn update_replay_hud(
+    mode: Res<ReplayMode>,
+    virtual_time: Res<Time<Virtual>>,
+    mut q_hud: Query<&mut Text, With<ReplayHud>>,
+) {
+    let (ReplayMode::Playback(playback), Ok(mut text)) = (&*mode, q_hud.single_mut()) else {
+        return;
+    };
+    let state = if playback.seek_to.is_some() {
+        "seeking".to_string()
+    } else if virtual_time.is_paused() {
+        "paused".to_string()
+    } else {
+        format!("x{}", SPEEDS[playback.speed])
+    };
+    text.0 = format!(
+        "REPLAY {} / {}  {state}",
+        clock(playback.tick.min(playback.recording.frames.len())),
+        clock(playback.recording.frames.len())
+    );
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let mode = ReplayMode::from_args();
+        // watching a replay replaces the run seed before anything draws from it
+        if let ReplayMode::Playback(playback) = &mode {
+            app.insert_resource(GameRng::new(playback.recording.seed, SeedSource::Chosen));
+        }
+        app.insert_resource(mode)
+            .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+            .init_resource::<LiveInput>()
+            .init_resource::<PlayerInput>()
+            .add_systems(Startup, (spawn_replay_hud, check_replay_arena))
+            .add_systems(
+                RunFixedMainLoop,
+                gather_live_input.in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+            )
+            .add_systems(
+                FixedUpdate,
+                (use_recorded_spawning, next_player_input)
+                    .chain()
+                    .in_set(GameSet::Input),
+            )
+            .add_systems(
+                Update,
+                (
+                    // the pause menu owns virtual time while it is open
+                    control_playback.run_if(not(in_state(PauseState::Paused))),
+                    update_replay_hud,
+                )
+                    .chain()
+                    .in_set(GameSet::Presentation),
+            )
+            .add_systems(Last, save_recording_on_end);
+    }
+}
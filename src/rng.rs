@@ -0,0 +1,214 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::components_and_resources::ConfigHandle;
+use crate::config::Config;
+
+// every random number in a run comes from one seed. Each system draws from its own ChaCha stream
+// of that seed, so a particle burst or a camera shake never shifts the meteor sequence: the same
+// seed always plays the same waves.
+//
+// The seed is picked from, in order: `--seed <n>` or `--daily` on the command line, a seed typed
+// into the pause menu last time (on platforms that can't relaunch themselves with `--seed`),
+// `seed` or `daily` in config.toml's [settings], or a random one.
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Independent sequences drawn from the run seed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    /// Meteor placement, size, speed and spin. The stream replays rely on.
+    Spawns,
+    Particles,
+    Camera,
+    Background,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedSource {
+    Random,
+    /// Today's shared seed, `day` is days since 1970-01-01 (UTC).
+    Daily {
+        day: u64,
+    },
+    /// Entered by the player.
+    Chosen,
+}
+
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    source: SeedSource,
+    streams: HashMap<RngStream, ChaCha8Rng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64, source: SeedSource) -> Self {
+        Self {
+            seed,
+            source,
+            streams: HashMap::default(),
+        }
+    }
+
+    /// A random seed, kept to 32 bits so it is short enough to read off and type back in.
+    pub fn random() -> Self {
+        Self::new(rand::random::<u32>() as u64, SeedSource::Random)
+    }
+
+    pub fn daily() -> Self {
+        let day = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() / SECONDS_PER_DAY);
+        Self::new(daily_seed(day), SeedSource::Daily { day })
+    }
+
+    /// Seed from `--seed <n>` or `--daily`, random otherwise.
+    pub fn from_args() -> Self {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--daily" => return Self::daily(),
+                "--seed" => match args.next().map(|seed| seed.parse::<u64>()) {
+                    Some(Ok(seed)) => return Self::new(seed, SeedSource::Chosen),
+                    _ => warn!("--seed needs a whole number, using a random seed"),
+                },
+                _ => {}
+            }
+        }
+        Self::random()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts every stream over from `seed`.
+    pub fn reseed(&mut self, seed: u64, source: SeedSource) {
+        *self = Self::new(seed, source);
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        self.streams.entry(stream).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(stream as u64);
+            rng
+        })
+    }
+
+    /// "1234567" or "1234567 (daily 2026-10-19)", for the game over screen.
+    pub fn describe(&self) -> String {
+        match self.source {
+            SeedSource::Daily { day } => format!("{} (daily {})", self.seed, date(day)),
+            SeedSource::Random | SeedSource::Chosen => self.seed.to_string(),
+        }
+    }
+}
+
+// where a seed typed into the pause menu waits for the next launch, None where the game can
+// relaunch itself with `--seed` instead
+#[cfg(target_os = "android")]
+fn next_seed_path() -> Option<PathBuf> {
+    bevy::window::ANDROID_APP
+        .get()
+        .and_then(|app| app.internal_data_path())
+        .map(|dir| dir.join("next_seed"))
+}
+
+#[cfg(not(target_os = "android"))]
+fn next_seed_path() -> Option<PathBuf> {
+    None
+}
+
+/// Keeps `seed` for the next launch. False where that isn't possible, relaunch with `--seed`.
+pub fn save_next_seed(seed: u64) -> bool {
+    let Some(path) = next_seed_path() else {
+        return false;
+    };
+    match std::fs::write(&path, seed.to_string()) {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("can't keep the seed for next time: {err}");
+            false
+        }
+    }
+}
+
+// the seed saved by save_next_seed, used once
+fn take_next_seed() -> Option<u64> {
+    let path = next_seed_path()?;
+    let seed = std::fs::read_to_string(&path).ok()?.trim().parse().ok();
+    let _ = std::fs::remove_file(&path);
+    seed
+}
+
+// splitmix64 of the day number, so consecutive days get unrelated seeds
+fn daily_seed(day: u64) -> u64 {
+    let mut z = day.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (z ^ (z >> 31)) & u32::MAX as u64
+}
+
+// days since 1970-01-01 as yyyy-mm-dd (Howard Hinnant's civil_from_days)
+fn date(day: u64) -> String {
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let d = day_of_year - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = year_of_era + era * 400 + i64::from(m <= 2);
+    format!("{y:04}-{m:02}-{d:02}")
+}
+
+/// Uses config.toml's seed once it has loaded, unless one was given on the command line.
+pub fn seed_from_config(
+    mut events: EventReader<AssetEvent<Config>>,
+    config_handle: Res<ConfigHandle>,
+    configs: Res<Assets<Config>>,
+    mut rng: ResMut<GameRng>,
+) {
+    for event in events.read() {
+        if !event.is_loaded_with_dependencies(&config_handle.0) || rng.source != SeedSource::Random
+        {
+            continue;
+        }
+        let Some(config) = configs.get(&config_handle.0) else {
+            continue;
+        };
+        if let Some(seed) = config.settings.seed {
+            rng.reseed(seed, SeedSource::Chosen);
+        } else if config.settings.daily {
+            *rng = GameRng::daily();
+        } else {
+            continue;
+        }
+        info!("run seed {} from config.toml", rng.describe());
+    }
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        // inserted before Startup so setup systems draw from the run seed too
+        let mut rng = GameRng::from_args();
+        if rng.source == SeedSource::Random
+            && let Some(seed) = take_next_seed()
+        {
+            rng.reseed(seed, SeedSource::Chosen);
+        }
+        info!("run seed {}", rng.describe());
+        app.insert_resource(rng)
+            .add_systems(PreUpdate, seed_from_config);
+    }
+}
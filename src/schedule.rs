@@ -0,0 +1,117 @@
+use bevy::app::{RunFixedMainLoop, RunFixedMainLoopSystem};
+use bevy::prelude::*;
+use bevy_rapier2d::plugin::PhysicsSet;
+
+use crate::pool::Pooled;
+
+// the game steps in FixedUpdate at replay::TICK_RATE, so it plays the same at any frame rate.
+// Each tick runs Input, then Simulation, then Rapier, then Collision, which reads what Rapier
+// wrote back. Presentation runs every frame in Update and only reacts to what the simulation
+// did. Moving things carry Interpolated so they glide between ticks instead of stepping.
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    /// Fills PlayerInput for the tick.
+    Input,
+    /// Firing, movement, spawning and everything else that changes the world before physics.
+    Simulation,
+    /// Collision events, damage, deaths and waves, after Rapier's writeback.
+    Collision,
+    /// Effects, UI, audio and camera, in Update.
+    Presentation,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pose {
+    translation: Vec3,
+    rotation: Quat,
+}
+
+impl Pose {
+    fn of(transform: &Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation,
+        }
+    }
+}
+
+/// Draws the entity between its poses at the last two ticks. Only for top-level entities the
+/// simulation moves.
+#[derive(Component, Default)]
+pub struct Interpolated {
+    previous: Option<Pose>,
+    current: Option<Pose>,
+}
+
+/// Puts moved entities back where the simulation left them before the next tick. The global
+/// transform goes back too, Rapier reads that to spot bodies that were moved by hand.
+pub fn restore_simulated_transforms(
+    mut q_interpolated: Query<
+        (&Interpolated, &mut Transform, &mut GlobalTransform),
+        Without<ChildOf>,
+    >,
+) {
+    for (interpolated, mut transform, mut global_transform) in q_interpolated.iter_mut() {
+        let Some(current) = interpolated.current else {
+            continue;
+        };
+        transform.translation = current.translation;
+        transform.rotation = current.rotation;
+        *global_transform = GlobalTransform::from(*transform);
+    }
+}
+
+pub fn snapshot_transforms(
+    mut q_interpolated: Query<(&mut Interpolated, &Transform)>,
+    mut reused: RemovedComponents<Pooled>,
+) {
+    for (mut interpolated, transform) in q_interpolated.iter_mut() {
+        let pose = Pose::of(transform);
+        interpolated.previous = Some(interpolated.current.unwrap_or(pose));
+        interpolated.current = Some(pose);
+    }
+    // entities fresh out of a pool jump to where they are used next, they don't travel there
+    for entity in reused.read() {
+        if let Ok((mut interpolated, transform)) = q_interpolated.get_mut(entity) {
+            interpolated.previous = Some(Pose::of(transform));
+        }
+    }
+}
+
+pub fn interpolate_transforms(
+    mut q_interpolated: Query<(&Interpolated, &mut Transform), Without<ChildOf>>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    let alpha = fixed_time.overstep_fraction();
+    for (interpolated, mut transform) in q_interpolated.iter_mut() {
+        let (Some(previous), Some(current)) = (interpolated.previous, interpolated.current) else {
+            continue;
+        };
+        transform.translation = previous.translation.lerp(current.translation, alpha);
+        transform.rotation = previous.rotation.slerp(current.rotation, alpha);
+    }
+}
+
+pub struct SchedulePlugin;
+
+impl Plugin for SchedulePlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(
+            FixedUpdate,
+            (
+                (GameSet::Input, GameSet::Simulation)
+                    .chain()
+                    .before(PhysicsSet::SyncBackend),
+                GameSet::Collision.after(PhysicsSet::Writeback),
+            ),
+        )
+        .configure_sets(Update, GameSet::Presentation)
+        .add_systems(FixedFirst, restore_simulated_transforms)
+        .add_systems(FixedLast, snapshot_transforms)
+        .add_systems(
+            RunFixedMainLoop,
+            interpolate_transforms.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+        );
+    }
+}
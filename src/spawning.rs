@@ -0,0 +1,246 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Layer;
+use crate::components_and_resources::WallSide;
+use crate::enemy::{self, MeteorSpawn};
+use crate::particles::SoftDotImage;
+use crate::schedule::GameSet;
+
+// where meteors come from. Each wave uses one SpawnStrategy (config.toml `[spawning]`), nothing
+// ever appears inside the safe radius around the turret and station, and every meteor is
+// announced by a pulsing marker before it arrives.
+
+// off-screen meteors start this far past the window edge, clear of the walls
+const OFFSCREEN_MARGIN: f32 = 120.0;
+// scattered meteors keep this far from the window edge, like they always have
+const SCATTER_MARGIN: f32 = 50.0;
+// edge meteors enter along the middle of a side and head for the middle of the screen
+const EDGE_SPREAD: f32 = 0.8;
+const TARGET_SPREAD: f32 = 0.6;
+const SCATTER_ATTEMPTS: u32 = 16;
+
+const WARNING_INSET: f32 = 30.0;
+const WARNING_COLOR: Color = Color::linear_rgba(2.5, 0.4, 0.2, 0.7);
+const WARNING_PULSES_PER_SECOND: f32 = 4.0;
+const WARNING_Z: f32 = 8.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnStrategy {
+    /// Anywhere on screen outside the safe radius, heading in a random direction.
+    Scatter,
+    /// From a random window edge, flying inwards.
+    Edges,
+    /// Like `Edges`, but the whole wave comes from the same side.
+    Flank,
+}
+
+/// Where a meteor starts, which way it heads and where its warning shows.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement {
+    pub position: Vec2,
+    pub direction: Vec2,
+    pub marker: Vec2,
+    /// Starts outside the walls and has to fly in.
+    pub offscreen: bool,
+}
+
+impl SpawnStrategy {
+    /// Places a meteor of `radius` in a window of `half` size. `protected` are the points it
+    /// must keep `safe_radius` away from.
+    pub fn place(
+        self,
+        rng: &mut impl Rng,
+        half: Vec2,
+        radius: f32,
+        wave: u32,
+        protected: &[Vec2],
+        safe_radius: f32,
+    ) -> Placement {
+        match self {
+            SpawnStrategy::Scatter => {
+                let bounds = (half - Vec2::splat(SCATTER_MARGIN)).max(Vec2::ONE);
+                for _ in 0..SCATTER_ATTEMPTS {
+                    let position = Vec2::new(
+                        rng.random_range(-bounds.x..bounds.x),
+                        rng.random_range(-bounds.y..bounds.y),
+                    );
+                    if protected
+                        .iter()
+                        .all(|point| point.distance(position) >= safe_radius + radius)
+                    {
+                        let direction =
+                            Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+                        return Placement {
+                            position,
+                            direction,
+                            marker: position,
+                            offscreen: false,
+                        };
+                    }
+                }
+                // the window is too small to keep clear of everything, come in from outside
+                SpawnStrategy::Edges.place(rng, half, radius, wave, protected, safe_radius)
+            }
+            SpawnStrategy::Edges => {
+                let side = window_side(rng.random_range(0..4));
+                edge_placement(rng, side, half, radius)
+            }
+            SpawnStrategy::Flank => edge_placement(rng, window_side(wave), half, radius),
+        }
+    }
+}
+
+fn window_side(index: u32) -> WallSide {
+    match index % 4 {
+        0 => WallSide::Left,
+        1 => WallSide::Right,
+        2 => WallSide::Top,
+        _ => WallSide::Bottom,
+    }
+}
+
+fn edge_placement(rng: &mut impl Rng, side: WallSide, half: Vec2, radius: f32) -> Placement {
+    let along = rng.random_range(-EDGE_SPREAD..EDGE_SPREAD);
+    let out = OFFSCREEN_MARGIN + radius;
+    let position = match side {
+        WallSide::Left => Vec2::new(-half.x - out, half.y * along),
+        WallSide::Right => Vec2::new(half.x + out, half.y * along),
+        WallSide::Top => Vec2::new(half.x * along, half.y + out),
+        WallSide::Bottom => Vec2::new(half.x * along, -half.y - out),
+    };
+    let spread = (half * TARGET_SPREAD).max(Vec2::ONE);
+    let target = Vec2::new(
+        rng.random_range(-spread.x..spread.x),
+        rng.random_range(-spread.y..spread.y),
+    );
+    let direction = (target - position).normalize_or(Vec2::X);
+    let marker = (position + direction * entry_distance(position, direction, half)).clamp(
+        -half + Vec2::splat(WARNING_INSET),
+        half - Vec2::splat(WARNING_INSET),
+    );
+    Placement {
+        position,
+        direction,
+        marker,
+        offscreen: true,
+    }
+}
+
+// how far `position` travels along `direction` before it crosses into the window
+fn entry_distance(position: Vec2, direction: Vec2, half: Vec2) -> f32 {
+    let near = (-half - position) / direction;
+    let far = (half - position) / direction;
+    near.min(far).max_element().max(0.0)
+}
+
+/// Marker shown where a meteor is about to turn up. It launches `meteor` at `launch_at` seconds
+/// and stays until the meteor is on screen.
+#[derive(Component)]
+pub struct SpawnWarning {
+    timer: Timer,
+    launch_at: f32,
+    meteor: Option<MeteorSpawn>,
+}
+
+/// A meteor still flying in from off-screen. It passes through the walls until it is fully
+/// inside, then bounces off them like any other.
+#[derive(Component)]
+pub struct Entering {
+    pub radius: f32,
+}
+
+/// Announces `meteor` at `placement.marker` and launches it `warning_seconds` later.
+pub fn telegraph(
+    commands: &mut Commands,
+    soft_dot: &SoftDotImage,
+    meteor: MeteorSpawn,
+    placement: &Placement,
+    radius: f32,
+    warning_seconds: f32,
+) {
+    // off-screen meteors keep the marker up for as long as they take to reach the edge
+    let travel =
+        (placement.position.distance(placement.marker) - radius).max(0.0) / meteor.speed.max(1.0);
+    let warning_seconds = warning_seconds.max(0.0);
+    commands.spawn((
+        Sprite {
+            image: soft_dot.0.clone(),
+            color: WARNING_COLOR,
+            custom_size: Some(Vec2::splat(radius * 3.0)),
+            ..default()
+        },
+        Transform::from_translation(placement.marker.extend(WARNING_Z)),
+        SpawnWarning {
+            timer: Timer::from_seconds(warning_seconds + travel, TimerMode::Once),
+            launch_at: warning_seconds,
+            meteor: Some(meteor),
+        },
+    ));
+}
+
+pub fn update_spawn_warnings(
+    mut commands: Commands,
+    mut q_warning: Query<(Entity, &mut SpawnWarning, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut warning, mut sprite) in q_warning.iter_mut() {
+        warning.timer.tick(time.delta());
+        if warning.timer.elapsed_secs() >= warning.launch_at
+            && let Some(meteor) = warning.meteor.take()
+        {
+            enemy::spawn_meteor(&mut commands, meteor);
+        }
+        if warning.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let pulse =
+            (warning.timer.elapsed_secs() * WARNING_PULSES_PER_SECOND * std::f32::consts::TAU)
+                .sin();
+        sprite
+            .color
+            .set_alpha(WARNING_COLOR.alpha() * (0.6 + 0.4 * pulse));
+    }
+}
+
+/// Gives entering meteors their wall collisions back once they are fully on screen.
+pub fn settle_entering_meteors(
+    mut commands: Commands,
+    q_entering: Query<(Entity, &Transform, &Entering)>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(win) = q_window.single() else {
+        return;
+    };
+    let half = win.size() / 2.0;
+    for (entity, transform, entering) in q_entering.iter() {
+        let position = transform.translation.truncate().abs();
+        if position.cmplt(half - Vec2::splat(entering.radius)).all() {
+            commands
+                .entity(entity)
+                .remove::<Entering>()
+                .insert(Layer::Enemy.groups());
+        } else if position
+            .cmpgt(half + Vec2::splat(2.0 * OFFSCREEN_MARGIN + entering.radius))
+            .any()
+        {
+            // knocked away by another meteor before making it in
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+pub struct SpawnPlugin;
+
+impl Plugin for SpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (update_spawn_warnings, settle_entering_meteors).in_set(GameSet::Simulation),
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_rapier2d::plugin::PhysicsSet;
+use bevy_rapier2d::prelude::*;
+
+use crate::schedule::GameSet;
+
+// pixels with an alpha at or below this are treated as empty space when outlining a sprite
+const ALPHA_THRESHOLD: f32 = 0.5;
+
+/// Gives the entity a convex collider outlining the opaque pixels of its `Sprite` image.
+///
+/// The collider is built in image pixel space once the image has loaded, so it lines up with
+/// the art at any `Transform` scale (rapier scales colliders with the transform).
+#[derive(Component)]
+pub struct SpriteCollider;
+
+// the image a collider was outlined from, plus the atlas frame for sprite sheets (the first
+// frame stands in for the whole clip)
+type ColliderKey = (AssetId<Image>, Option<URect>);
+
+/// What an entity's current collider was generated from, so it can be rebuilt when the sprite
+/// switches to different art.
+#[derive(Component, PartialEq)]
+pub struct SpriteColliderKey(ColliderKey);
+
+// one outline per image, meteors and bullets share theirs instead of rescanning the pixels
+#[derive(Resource, Default)]
+pub struct SpriteColliderCache(pub HashMap<ColliderKey, Collider>);
+
+/// Convex hull around the opaque pixels of `image` (or of `rect` within it), centred the same
+/// way a `Sprite` is drawn. Falls back to a ball covering the area if it can't be read or has
+/// no opaque pixels.
+pub fn collider_from_image(image: &Image, rect: Option<URect>) -> Collider {
+    let rect = rect.unwrap_or(URect::new(0, 0, image.width(), image.height()));
+    let width = rect.width();
+    let height = rect.height();
+    let half = Vec2::new(width as f32, height as f32) / 2.0;
+    let is_opaque = |x: u32, y: u32| {
+        image
+            .get_color_at(rect.min.x + x, rect.min.y + y)
+            .is_ok_and(|color| color.alpha() > ALPHA_THRESHOLD)
+    };
+
+    // the outermost opaque pixels of every row are enough to define the hull, push both
+    // horizontal edges of those pixels so the hull covers them entirely
+    let mut points = Vec::new();
+    for y in 0..height {
+        let Some(left) = (0..width).find(|&x| is_opaque(x, y)) else {
+            continue;
+        };
+        let right = (left..width)
+            .rev()
+            .find(|&x| is_opaque(x, y))
+            .unwrap_or(left);
+        let top = half.y - y as f32;
+        let bottom = top - 1.0;
+        for x in [left as f32, right as f32 + 1.0] {
+            points.push(Vect::new(x - half.x, top));
+            points.push(Vect::new(x - half.x, bottom));
+        }
+    }
+
+    Collider::convex_hull(&points).unwrap_or_else(|| Collider::ball(half.min_element()))
+}
+
+// None while the sprite's atlas layout is still loading
+fn collider_key(sprite: &Sprite, layouts: &Assets<TextureAtlasLayout>) -> Option<ColliderKey> {
+    let rect = match &sprite.texture_atlas {
+        Some(atlas) => Some(*layouts.get(&atlas.layout)?.textures.first()?),
+        None => None,
+    };
+    Some((sprite.image.id(), rect))
+}
+
+#[allow(clippy::type_complexity)]
+pub fn attach_sprite_colliders(
+    mut commands: Commands,
+    q_pending: Query<(Entity, &Sprite), (With<SpriteCollider>, Without<Collider>)>,
+    images: Res<Assets<Image>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut cache: ResMut<SpriteColliderCache>,
+) {
+    for (entity, sprite) in q_pending.iter() {
+        let Some(key) = collider_key(sprite, &layouts) else {
+            continue;
+        };
+        let collider = match cache.0.get(&key) {
+            Some(collider) => collider.clone(),
+            None => {
+                // image still loading, try again next frame
+                let Some(image) = images.get(key.0) else {
+                    continue;
+                };
+                let collider = collider_from_image(image, key.1);
+                cache.0.insert(key, collider.clone());
+                collider
+            }
+        };
+        commands
+            .entity(entity)
+            .insert((collider, SpriteColliderKey(key)));
+    }
+}
+
+/// Drops the collider of a sprite whose art changed (e.g. swapped onto an animation atlas), the
+/// attach system then outlines the new art.
+pub fn refresh_sprite_colliders(
+    mut commands: Commands,
+    q_sprite: Query<(Entity, &Sprite, &SpriteColliderKey), Changed<Sprite>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+) {
+    for (entity, sprite, current) in q_sprite.iter() {
+        let Some(key) = collider_key(sprite, &layouts) else {
+            continue;
+        };
+        if current.0 != key {
+            commands
+                .entity(entity)
+                .remove::<(Collider, SpriteColliderKey)>();
+        }
+    }
+}
+
+pub struct SpriteColliderPlugin;
+
+impl Plugin for SpriteColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpriteColliderCache>().add_systems(
+            FixedUpdate,
+            // after everything that spawns bodies, so they have a collider for their first step
+            (refresh_sprite_colliders, attach_sprite_colliders)
+                .chain()
+                .after(GameSet::Simulation)
+                .before(PhysicsSet::SyncBackend),
+        );
+    }
+}
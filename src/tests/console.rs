@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+
+use super::TestGame;
+use crate::boss::Boss;
+use crate::components_and_resources::{Enemy, Player};
+use crate::console::ConsoleReply;
+use crate::console::panel::{self, Console};
+use crate::damage::{DamageEvent, DamageKind, Health, Invincible};
+use crate::player::Weapon;
+use crate::replay::{Recording, ReplayMode};
+use crate::waves::Waves;
+
+fn run(game: &mut TestGame, line: &str) -> ConsoleReply {
+    panel::execute(game.world(), line)
+}
+
+fn player(game: &mut TestGame) -> Entity {
+    let world = game.world();
+    world
+        .query_filtered::<Entity, With<Player>>()
+        .single(world)
+        .unwrap()
+}
+
+#[test]
+fn unknown_commands_and_bad_arguments_are_errors() {
+    let mut game = TestGame::new();
+    assert!(
+        run(&mut game, "fly")
+            .unwrap_err()
+            .contains("unknown command")
+    );
+    let error = run(&mut game, "wave lots").unwrap_err();
+    assert!(error.contains("wave <number>"), "{error}");
+    assert!(
+        run(&mut game, "help")
+            .unwrap()
+            .contains("spawn <meteor|boss>")
+    );
+}
+
+#[test]
+fn spawn_puts_enemies_where_asked() {
+    let mut game = TestGame::new();
+    run(&mut game, "spawn meteor 200 -100").unwrap();
+    game.tick();
+    let world = game.world();
+    let transform = world
+        .query_filtered::<&Transform, With<Enemy>>()
+        .single(world)
+        .unwrap();
+    assert_eq!(transform.translation.truncate(), Vec2::new(200.0, -100.0));
+
+    run(&mut game, "spawn boss 0 100 2").unwrap();
+    assert_eq!(game.count::<With<Boss>>(), 1);
+    assert!(run(&mut game, "spawn dragon 0 0").is_err());
+}
+
+#[test]
+fn wave_jumps_ahead() {
+    let mut game = TestGame::new();
+    assert_eq!(run(&mut game, "wave 3").unwrap(), "wave 3, a boss wave");
+    let waves = game.world().resource::<Waves>();
+    assert_eq!(waves.number, 3);
+    assert_eq!(waves.kills, 0);
+    assert!(run(&mut game, "wave 0").is_err());
+}
+
+#[test]
+fn recorded_runs_refuse_commands_that_change_them() {
+    let mut game = TestGame::new();
+    game.world().insert_resource(ReplayMode::Recording {
+        path: "unused.replay".into(),
+        recording: Recording::default(),
+        saved: false,
+    });
+    let error = run(&mut game, "wave 3").unwrap_err();
+    assert!(error.contains("recording"), "{error}");
+    assert_eq!(game.world().resource::<Waves>().number, 1);
+    let player = player(&mut game);
+    assert!(run(&mut game, "god").is_err());
+    assert!(!game.has::<Invincible>(player));
+    assert!(run(&mut game, "help").is_ok());
+}
+
+#[test]
+fn god_mode_ignores_damage() {
+    let mut game = TestGame::new();
+    let player = player(&mut game);
+    assert_eq!(run(&mut game, "god").unwrap(), "god mode on");
+    assert!(game.has::<Invincible>(player));
+
+    game.world().send_event(DamageEvent {
+        target: player,
+        amount: 50.0,
+        kind: DamageKind::Impact,
+        source: None,
+    });
+    game.tick();
+    let health = game.world().get::<Health>(player).unwrap();
+    assert_eq!(health.current, health.max);
+
+    assert_eq!(run(&mut game, "god").unwrap(), "god mode off");
+    assert!(!game.has::<Invincible>(player));
+}
+
+#[test]
+fn give_swaps_the_weapon() {
+    let mut game = TestGame::new();
+    run(&mut game, "give scatter").unwrap();
+    let player = player(&mut game);
+    assert_eq!(game.world().get::<Weapon>(player).unwrap().name, "scatter");
+    assert!(run(&mut game, "give banana").is_err());
+
+    game.fire_at(Vec2::new(500.0, 0.0));
+    assert_eq!(game.accuracy().0, 3.0);
+}
+
+#[test]
+fn entities_lists_what_is_alive() {
+    let mut game = TestGame::new();
+    let reply = run(&mut game, "entities").unwrap();
+    assert!(reply.contains("Player"), "{reply}");
+}
+
+#[test]
+fn typing_in_the_console_runs_commands_and_keeps_keys_from_the_game() {
+    let mut game = TestGame::new();
+    game.tap(KeyCode::Backquote);
+    assert!(game.world().resource::<Console>().open);
+
+    game.type_text("wave 2\n");
+    assert_eq!(game.world().resource::<Waves>().number, 2);
+    // the space typed above didn't fire
+    assert_eq!(game.accuracy().0, 0.0);
+
+    game.tap(KeyCode::Backquote);
+    assert!(!game.world().resource::<Console>().open);
+}
@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+
+use super::TestGame;
+use crate::debug_overlay::DebugOverlay;
+
+#[test]
+fn f3_toggles_the_overlay() {
+    let mut game = TestGame::new();
+    let overlay = |game: &mut TestGame| {
+        let world = game.world();
+        *world
+            .query_filtered::<&Visibility, With<DebugOverlay>>()
+            .single(world)
+            .unwrap()
+    };
+    assert_eq!(overlay(&mut game), Visibility::Hidden);
+
+    game.tap(KeyCode::F3);
+    assert_eq!(overlay(&mut game), Visibility::Visible);
+    game.tap(KeyCode::F3);
+    assert_eq!(overlay(&mut game), Visibility::Hidden);
+}
+
+#[test]
+fn overlay_counts_what_is_alive() {
+    let mut game = TestGame::new();
+    game.spawn_meteor(Vec2::new(300.0, 0.0), 0.025);
+    game.spawn_meteor(Vec2::new(-300.0, 0.0), 0.025);
+    game.spawn_bullet(Vec2::ZERO);
+    game.tap(KeyCode::F3);
+
+    let text = game.text::<DebugOverlay>();
+    assert!(text.contains("Meteors: 2"), "{text}");
+    assert!(text.contains("Bullets: 1"), "{text}");
+    assert!(text.contains("Spawn timer: "), "{text}");
+    assert!(text.contains("Wave: 1"), "{text}");
+}
@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+use bevy::render::mesh::MeshTag;
+use std::time::{Duration, Instant};
+
+use super::TestGame;
+use crate::animation::SpriteAnimation;
+use crate::camera_fx::{self, AddTrauma, HitStop};
+use crate::components_and_resources::Player;
+use crate::damage_feedback::{HitFlash, HitFlashMaterial};
+use crate::lighting::{self, Light2d, LightGlow};
+use crate::particles::{
+    self, EffectPresets, EffectPresetsHandle, Particle, ParticleEmitter, ParticleMaterial,
+};
+
+const BACKWARDS: &str = r#"
+[emitters.backwards]
+burst = 20
+duration = 0.1
+lifetime = [0.9, 0.4]
+speed = [260.0, 60.0]
+cone = -45.0
+size = [[0.0, 10.0], [1.0, 2.0]]
+color = [[0.0, 1.0, 0.75, 0.4, 1.0], [1.0, 0.3, 0.3, 0.3, 0.0]]
+additive = true
+"#;
+
+fn use_presets(game: &mut TestGame, text: &str) {
+    let mut presets: EffectPresets = toml::from_str(text).unwrap();
+    presets.fix_ranges();
+    // a handle of their own, effects.fx.toml finishing loading would replace them otherwise
+    let world = game.world();
+    let handle = world.resource_mut::<Assets<EffectPresets>>().add(presets);
+    world.insert_resource(EffectPresetsHandle(handle));
+}
+
+#[test]
+fn backwards_ranges_are_fixed_on_load() {
+    let mut presets: EffectPresets = toml::from_str(BACKWARDS).unwrap();
+    presets.fix_ranges();
+    let preset = &presets.emitters["backwards"];
+    assert_eq!(preset.lifetime, [0.4, 0.9]);
+    assert_eq!(preset.speed, [60.0, 260.0]);
+    assert_eq!(preset.cone, 45.0);
+}
+
+#[test]
+fn additive_presets_share_one_material() {
+    let mut game = TestGame::new();
+    use_presets(&mut game, BACKWARDS);
+    for _ in 0..2 {
+        particles::spawn_effect(
+            &mut game.world().commands(),
+            "backwards",
+            Transform::default(),
+        );
+    }
+    game.app.update();
+    game.app.update();
+
+    assert_eq!(game.count::<With<Particle>>(), 40);
+    assert_eq!(game.count::<(With<Particle>, With<Sprite>)>(), 0);
+    let world = game.world();
+    let drawn: Vec<_> = world
+        .query_filtered::<(&MeshMaterial2d<ParticleMaterial>, &MeshTag), With<Particle>>()
+        .iter(world)
+        .map(|(material, tint)| (material.0.id(), tint.0))
+        .collect();
+    assert_eq!(drawn.len(), 40);
+    assert!(drawn.iter().all(|(material, _)| *material == drawn[0].0));
+    assert_eq!(world.resource::<Assets<ParticleMaterial>>().len(), 1);
+    // fading out over their life without touching the material
+    assert!(
+        drawn
+            .iter()
+            .all(|(_, tint)| *tint != particles::particle_tint(Color::WHITE).0)
+    );
+}
+
+#[test]
+fn particle_tints_pack_a_byte_a_channel() {
+    let tint = particles::particle_tint(LinearRgba::new(1.0, 0.5, 0.0, 2.0));
+    assert_eq!(tint.0, 0xff_00_80_ff);
+}
+
+#[test]
+fn hits_flash_with_an_overlay_that_fades_out() {
+    let mut game = TestGame::new();
+    // big enough to survive one bullet
+    let meteor = game.spawn_meteor(Vec2::new(300.0, 0.0), 0.05);
+    let world = game.world();
+    let image = world
+        .resource_mut::<Assets<Image>>()
+        .add(particles::soft_dot(8));
+    world.get_mut::<Sprite>(meteor).unwrap().image = image;
+
+    let bullet = game.spawn_bullet(Vec2::new(300.0, 0.0));
+    game.collide(bullet, meteor);
+    game.tick();
+    game.app.update();
+    assert!(game.has::<HitFlash>(meteor));
+    let world = game.world();
+    let (parent, overlay) = world
+        .query_filtered::<(&ChildOf, &Transform), With<MeshMaterial2d<HitFlashMaterial>>>()
+        .single(world)
+        .unwrap();
+    assert_eq!(parent.parent(), meteor);
+    assert_eq!(overlay.scale, Vec3::new(8.0, 8.0, 1.0));
+
+    game.run_for(0.2);
+    assert!(!game.has::<HitFlash>(meteor));
+    assert_eq!(game.count::<With<MeshMaterial2d<HitFlashMaterial>>>(), 0);
+}
+
+#[derive(Resource, Default)]
+struct CameraKicks {
+    trauma: Vec<f32>,
+    hit_stops: usize,
+}
+
+fn record_camera_kicks(
+    mut trauma: EventReader<AddTrauma>,
+    mut hit_stops: EventReader<HitStop>,
+    mut kicks: ResMut<CameraKicks>,
+) {
+    kicks
+        .trauma
+        .extend(trauma.read().map(|AddTrauma(amount)| *amount));
+    kicks.hit_stops += hit_stops.read().count();
+}
+
+// CameraFxPlugin isn't in the test game, only the system that turns hits into camera kicks
+fn watch_camera(game: &mut TestGame) {
+    game.app
+        .add_event::<AddTrauma>()
+        .add_event::<HitStop>()
+        .init_resource::<CameraKicks>()
+        .add_systems(
+            Update,
+            (camera_fx::react_to_hits, record_camera_kicks).chain(),
+        );
+}
+
+#[test]
+fn killing_a_big_meteor_shakes_and_stops_the_game() {
+    let mut game = TestGame::new();
+    watch_camera(&mut game);
+    // two bullets for the biggest meteors
+    let meteor = game.spawn_meteor(Vec2::new(300.0, 0.0), 0.05);
+    for _ in 0..2 {
+        let bullet = game.spawn_bullet(Vec2::new(300.0, 0.0));
+        game.collide(bullet, meteor);
+        game.tick();
+    }
+    game.app.update();
+    assert!(!game.exists(meteor));
+
+    let kicks = game.world().resource::<CameraKicks>();
+    // a hit, then the killing hit and the kill
+    assert_eq!(kicks.trauma.len(), 3, "{:?}", kicks.trauma);
+    assert_eq!(kicks.hit_stops, 1);
+}
+
+#[test]
+fn killing_a_small_meteor_shakes_without_stopping() {
+    let mut game = TestGame::new();
+    watch_camera(&mut game);
+    let meteor = game.spawn_meteor(Vec2::new(300.0, 0.0), 0.025);
+    let bullet = game.spawn_bullet(Vec2::new(300.0, 0.0));
+    game.collide(bullet, meteor);
+    game.tick();
+    game.app.update();
+    assert!(!game.exists(meteor));
+
+    let kicks = game.world().resource::<CameraKicks>();
+    assert_eq!(kicks.trauma.len(), 2, "{:?}", kicks.trauma);
+    assert_eq!(kicks.hit_stops, 0);
+}
+
+fn muzzle_flashes(game: &mut TestGame) -> usize {
+    let world = game.world();
+    world
+        .query::<&ParticleEmitter>()
+        .iter(world)
+        .filter(|emitter| emitter.preset == "muzzle_flash")
+        .count()
+}
+
+#[test]
+fn the_fire_clip_flashes_the_muzzle_on_its_event_frame() {
+    let mut game = TestGame::new();
+    // clips.anim.toml and the sheet load from disk, slowly when every test is loading at once
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        let world = game.world();
+        let animated = world
+            .query_filtered::<&Sprite, (With<Player>, With<SpriteAnimation>)>()
+            .iter(world)
+            .any(|sprite| sprite.texture_atlas.is_some());
+        if animated {
+            break;
+        }
+        game.app.update();
+    }
+    assert_eq!(game.count::<(With<Player>, With<SpriteAnimation>)>(), 1);
+
+    game.fire_at(Vec2::new(300.0, 0.0));
+    // the clip's first frame has no event, the flash waits for the next one
+    assert_eq!(muzzle_flashes(&mut game), 0);
+    game.app.update();
+    game.app.update();
+    assert_eq!(muzzle_flashes(&mut game), 1);
+}
+
+#[test]
+fn light_glows_add_to_the_frame_with_a_shared_material() {
+    let mut game = TestGame::new();
+    // LightingPlugin isn't in the test game
+    game.app.add_systems(
+        Update,
+        (lighting::attach_light_glows, lighting::update_light_glows).chain(),
+    );
+    let light = Light2d {
+        color: Color::srgb(1.0, 0.5, 0.0),
+        radius: 50.0,
+        intensity: 2.0,
+    };
+    for x in [0.0, 200.0] {
+        lighting::spawn_flash(
+            &mut game.world().commands(),
+            Vec3::new(x, 0.0, 0.0),
+            light,
+            1.0,
+        );
+    }
+    game.app.update();
+    game.app.update();
+
+    assert_eq!(game.count::<(With<LightGlow>, With<Sprite>)>(), 0);
+    let world = game.world();
+    let glows: Vec<_> = world
+        .query_filtered::<(&MeshMaterial2d<ParticleMaterial>, &Transform), With<LightGlow>>()
+        .iter(world)
+        .map(|(material, transform)| (material.0.id(), transform.scale))
+        .collect();
+    assert_eq!(glows.len(), 2);
+    assert_eq!(glows[0].0, glows[1].0);
+    assert_eq!(glows[0].1, Vec3::new(100.0, 100.0, 1.0));
+    let materials = world.resource::<Assets<ParticleMaterial>>();
+    assert_eq!(
+        materials.get(glows[0].0).unwrap().tint,
+        LinearRgba::rgb(2.0, 2.0, 2.0)
+    );
+}
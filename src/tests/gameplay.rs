@@ -0,0 +1,259 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::{SPAWN_SECONDS, TestGame};
+use crate::components_and_resources::{Accuracy, Bullet, Enemy, Player, Score};
+use crate::game_plugin::GamePlugin;
+use crate::pool::Pooled;
+use crate::spawning::SpawnWarning;
+use crate::waves::Waves;
+
+// 2000 × scale health: one bullet for the smallest meteors, two for the biggest
+const SMALL_METEOR: f32 = 0.025;
+const BIG_METEOR: f32 = 0.05;
+
+#[test]
+fn bullet_destroys_small_meteor_and_scores() {
+    let mut game = TestGame::new();
+    let meteor = game.spawn_meteor(Vec2::new(300.0, 0.0), SMALL_METEOR);
+    let bullet = game.spawn_bullet(Vec2::new(300.0, 0.0));
+
+    game.collide(bullet, meteor);
+    game.tick();
+
+    assert!(!game.exists(meteor));
+    assert_eq!(game.score(), 1);
+    assert_eq!(game.text::<Score>(), "Score: 1");
+    assert_eq!(game.accuracy().1, 1.0);
+}
+
+#[test]
+fn big_meteor_takes_two_hits() {
+    let mut game = TestGame::new();
+    let meteor = game.spawn_meteor(Vec2::new(300.0, 0.0), BIG_METEOR);
+
+    let first = game.spawn_bullet(Vec2::new(300.0, 0.0));
+    game.collide(first, meteor);
+    game.tick();
+    assert!(game.exists(meteor));
+    assert_eq!(game.score(), 0);
+
+    let second = game.spawn_bullet(Vec2::new(300.0, 0.0));
+    game.collide(second, meteor);
+    game.tick();
+    assert!(!game.exists(meteor));
+    assert_eq!(game.score(), 1);
+}
+
+#[test]
+fn bullet_touching_two_meteors_hits_once() {
+    let mut game = TestGame::new();
+    let first = game.spawn_meteor(Vec2::new(300.0, 0.0), SMALL_METEOR);
+    let second = game.spawn_meteor(Vec2::new(310.0, 0.0), SMALL_METEOR);
+    let bullet = game.spawn_bullet(Vec2::new(305.0, 0.0));
+
+    game.collide(bullet, first);
+    game.collide(second, bullet);
+    game.tick();
+
+    assert!(!game.exists(first));
+    assert!(game.exists(second));
+    assert_eq!(game.score(), 1);
+    assert_eq!(game.accuracy().1, 1.0);
+}
+
+#[test]
+fn two_bullets_in_one_tick_both_hit() {
+    let mut game = TestGame::new();
+    let meteor = game.spawn_meteor(Vec2::new(300.0, 0.0), BIG_METEOR);
+    let first = game.spawn_bullet(Vec2::new(300.0, 0.0));
+    let second = game.spawn_bullet(Vec2::new(300.0, 0.0));
+
+    game.collide(first, meteor);
+    game.collide(meteor, second);
+    game.tick();
+
+    assert!(!game.exists(meteor));
+    assert_eq!(game.score(), 1);
+    assert_eq!(game.accuracy().1, 2.0);
+}
+
+#[test]
+fn firing_counts_shots_and_updates_accuracy_text() {
+    let mut game = TestGame::new();
+    game.fire_at(Vec2::new(0.0, 400.0));
+    game.fire_at(Vec2::new(0.0, -400.0));
+
+    assert_eq!(game.accuracy(), (2.0, 0.0));
+    assert_eq!(game.count::<(With<Bullet>, Without<Pooled>)>(), 2);
+    assert_eq!(game.text::<Accuracy>(), "Accuracy: 0");
+
+    let meteor = game.spawn_meteor(Vec2::new(600.0, 300.0), SMALL_METEOR);
+    let world = game.world();
+    let bullet = world
+        .query_filtered::<Entity, (With<Bullet>, Without<Pooled>)>()
+        .iter(world)
+        .next()
+        .unwrap();
+    game.collide(bullet, meteor);
+    game.tick();
+
+    assert_eq!(game.accuracy(), (2.0, 1.0));
+    assert_eq!(game.text::<Accuracy>(), "Accuracy: 50");
+}
+
+#[test]
+fn fired_bullets_fly_towards_the_cursor() {
+    let mut game = TestGame::new();
+    game.fire_at(Vec2::new(500.0, 0.0));
+    game.run_for(0.5);
+
+    let world = game.world();
+    let transform = world
+        .query_filtered::<&Transform, With<Bullet>>()
+        .single(world)
+        .unwrap();
+    assert!(transform.translation.x > 100.0);
+    assert!(transform.translation.y.abs() < 1.0);
+}
+
+#[test]
+fn bullet_hitting_a_wall_goes_back_to_the_pool_and_is_reused() {
+    let mut game = TestGame::new();
+    game.fire_at(Vec2::new(500.0, 0.0));
+    let world = game.world();
+    let bullet = world
+        .query_filtered::<Entity, With<Bullet>>()
+        .single(world)
+        .unwrap();
+    let wall = game.spawn_wall();
+
+    game.collide(bullet, wall);
+    game.tick();
+    assert!(game.has::<Pooled>(bullet));
+    assert!(!game.has::<Bullet>(bullet));
+    assert_eq!(
+        game.world().get::<Visibility>(bullet),
+        Some(&Visibility::Hidden)
+    );
+
+    game.fire_at(Vec2::new(-500.0, 0.0));
+    assert!(game.has::<Bullet>(bullet));
+    assert!(!game.has::<Pooled>(bullet));
+}
+
+#[test]
+fn missed_bullets_are_retired() {
+    let mut game = TestGame::new();
+    game.fire_at(Vec2::new(0.0, 400.0));
+    let world = game.world();
+    let bullet = world
+        .query_filtered::<Entity, With<Bullet>>()
+        .single(world)
+        .unwrap();
+
+    // out of the arena or out of time, whichever comes first
+    game.run_for(3.0);
+    assert_eq!(game.count::<With<Bullet>>(), 0);
+    assert!(game.has::<Pooled>(bullet));
+}
+
+#[test]
+fn spawn_timer_warns_then_launches_a_meteor() {
+    let mut game = TestGame::new();
+    let spawn_ticks = (SPAWN_SECONDS as f64 * crate::replay::TICK_RATE) as u32;
+
+    game.ticks(spawn_ticks - 1);
+    assert_eq!(game.count::<With<SpawnWarning>>(), 0);
+    game.tick();
+    assert_eq!(game.count::<With<SpawnWarning>>(), 1);
+    assert_eq!(game.count::<With<Enemy>>(), 0);
+
+    // the warning shows for warning_seconds (0.8 in config.toml) before the meteor arrives
+    game.run_for(0.8);
+    assert_eq!(game.count::<With<Enemy>>(), 1);
+
+    game.run_for(SPAWN_SECONDS as f64);
+    assert_eq!(game.count::<With<Enemy>>(), 2);
+}
+
+#[test]
+fn boss_waves_spawn_no_meteors() {
+    let mut game = TestGame::new();
+    game.world().resource_mut::<Waves>().number = 3;
+
+    game.run_for(3.0 * SPAWN_SECONDS as f64);
+    assert_eq!(game.count::<With<SpawnWarning>>(), 0);
+    assert_eq!(game.count::<With<Enemy>>(), 0);
+}
+
+#[test]
+fn twelve_kills_start_the_next_wave() {
+    let mut game = TestGame::new();
+    for _ in 0..12 {
+        let meteor = game.spawn_meteor(Vec2::new(300.0, 0.0), SMALL_METEOR);
+        let bullet = game.spawn_bullet(Vec2::new(300.0, 0.0));
+        game.collide(bullet, meteor);
+        game.tick();
+    }
+    assert_eq!(game.world().resource::<Waves>().number, 2);
+    assert_eq!(game.score(), 12);
+}
+
+#[test]
+fn accuracy_text_without_accuracy_does_nothing() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_systems(Update, GamePlugin::update_accuracy_text);
+    app.update();
+}
+
+#[test]
+fn hits_still_land_without_accuracy() {
+    let mut game = TestGame::new();
+    let world = game.world();
+    let accuracy = world
+        .query_filtered::<Entity, With<Accuracy>>()
+        .single(world)
+        .unwrap();
+    world.despawn(accuracy);
+
+    let meteor = game.spawn_meteor(Vec2::new(300.0, 0.0), SMALL_METEOR);
+    let bullet = game.spawn_bullet(Vec2::new(300.0, 0.0));
+    game.collide(bullet, meteor);
+    game.tick();
+
+    assert!(!game.exists(meteor));
+    assert_eq!(game.score(), 1);
+}
+
+#[test]
+fn game_keeps_running_without_the_player() {
+    let mut game = TestGame::new();
+    let world = game.world();
+    let player = world
+        .query_filtered::<Entity, With<Player>>()
+        .single(world)
+        .unwrap();
+    world.despawn(player);
+
+    game.fire_at(Vec2::new(500.0, 0.0));
+    game.run_for(2.0 * SPAWN_SECONDS as f64);
+    assert_eq!(game.accuracy(), (0.0, 0.0));
+    assert_eq!(game.count::<With<Bullet>>(), 0);
+}
+
+#[test]
+fn game_keeps_running_without_a_window() {
+    let mut game = TestGame::new();
+    let world = game.world();
+    let window = world
+        .query_filtered::<Entity, With<PrimaryWindow>>()
+        .single(world)
+        .unwrap();
+    world.despawn(window);
+
+    game.run_for(2.0 * SPAWN_SECONDS as f64);
+    // nowhere to place meteors without a window
+    assert_eq!(game.count::<With<SpawnWarning>>(), 0);
+}
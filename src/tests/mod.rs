@@ -0,0 +1,285 @@
+use bevy::ecs::query::QueryFilter;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput, NativeKey};
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::window::PrimaryWindow;
+use bevy_rapier2d::prelude::CollisionEvent;
+use bevy_rapier2d::rapier::geometry::CollisionEventFlags;
+
+use crate::components_and_resources::{
+    Accuracy, Bullet, ConfigHandle, EnemySapwnTimer, GameSpeed, Level, Score, Wall, WallSide,
+};
+use crate::config::Config;
+use crate::damage::{DamageDealer, DamageKind};
+use crate::enemy::{self, MeteorSpawn};
+use crate::rng::{GameRng, SeedSource};
+use crate::{
+    animation, collision, damage, damage_feedback, enemy::EnemyPlugin, game,
+    game_plugin::GamePlugin, headless, particles, pause::PausePlugin, player::PlayerPlugin, pool,
+    replay, rng, schedule, spawning, waves,
+};
+
+// the console and overlay tests only build with dev-tools, CI runs `cargo test` both with and
+// without the feature
+#[cfg(feature = "dev-tools")]
+mod console;
+#[cfg(feature = "dev-tools")]
+mod debug_overlay;
+mod effects;
+mod gameplay;
+mod pause;
+mod recording;
+
+// gameplay tests run the game's own plugins in a windowless App, one fixed tick per step. Rapier
+// isn't added: tests put entities where they want them and send the CollisionEvents rapier
+// would have sent, so what is tested is the game's reaction to a contact, not the physics.
+
+pub const SPAWN_SECONDS: f32 = 2.0;
+const SEED: u64 = 1;
+
+pub struct TestGame {
+    pub app: App,
+}
+
+impl TestGame {
+    /// GamePlugin, PlayerPlugin, EnemyPlugin and PausePlugin with the plugins they lean on, config.toml
+    /// already loaded and a fixed seed.
+    pub fn new() -> Self {
+        let mut app = App::new();
+        headless::add_headless_plugins(&mut app);
+        app.add_event::<CollisionEvent>()
+            .add_plugins(StatesPlugin)
+            .add_plugins((
+                schedule::SchedulePlugin,
+                rng::RngPlugin,
+                replay::ReplayPlugin,
+                pool::PoolPlugin,
+                collision::CollisionPlugin,
+                damage::DamagePlugin,
+                damage_feedback::DamageFeedbackPlugin,
+                waves::WavePlugin,
+                spawning::SpawnPlugin,
+                particles::ParticlePlugin,
+                animation::AnimationPlugin,
+            ))
+            .add_plugins((GamePlugin, PlayerPlugin, EnemyPlugin, PausePlugin))
+            .insert_resource(EnemySapwnTimer(Timer::from_seconds(
+                SPAWN_SECONDS,
+                TimerMode::Repeating,
+            )))
+            .insert_resource(GameRng::new(SEED, SeedSource::Chosen))
+            // owned by CameraFxPlugin and BackgroundPlugin, which only draw
+            .init_resource::<GameSpeed>()
+            .init_resource::<Level>()
+            .init_asset::<Config>();
+        game::add_collision_handlers(&mut app);
+        #[cfg(feature = "dev-tools")]
+        app.add_plugins((
+            crate::debug_overlay::DebugOverlayPlugin,
+            crate::console::ConsolePlugin,
+        ));
+        headless::finish_plugins(&mut app);
+
+        let config: Config = toml::from_str(include_str!("../../assets/config.toml"))
+            .expect("assets/config.toml parses");
+        let world = app.world_mut();
+        let handle = world.resource_mut::<Assets<Config>>().add(config);
+        world.insert_resource(ConfigHandle(handle));
+
+        let mut game = Self { app };
+        // Startup
+        game.app.update();
+        game
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    /// Steps until exactly one more fixed tick has run.
+    pub fn tick(&mut self) {
+        let before = self.app.world().resource::<Time<Fixed>>().elapsed();
+        while self.app.world().resource::<Time<Fixed>>().elapsed() == before {
+            self.app.update();
+        }
+    }
+
+    pub fn ticks(&mut self, count: u32) {
+        for _ in 0..count {
+            self.tick();
+        }
+    }
+
+    /// Ticks for at least `seconds` of game time.
+    pub fn run_for(&mut self, seconds: f64) {
+        self.ticks((seconds * replay::TICK_RATE).ceil() as u32);
+    }
+
+    /// Points the mouse at `target` in world space and taps Space, the same way a player fires.
+    pub fn fire_at(&mut self, target: Vec2) {
+        let world = self.world();
+        let mut q_window = world.query_filtered::<(Entity, &mut Window), With<PrimaryWindow>>();
+        let (window, mut win) = q_window.single_mut(world).expect("headless window");
+        let half = win.size() / 2.0;
+        win.set_cursor_position(Some(Vec2::new(target.x + half.x, half.y - target.y)));
+        self.key(window, KeyCode::Space, ButtonState::Pressed);
+        self.tick();
+        self.key(window, KeyCode::Space, ButtonState::Released);
+    }
+
+    /// Presses and releases `key_code` over one frame.
+    pub fn tap(&mut self, key_code: KeyCode) {
+        let world = self.world();
+        let window = world
+            .query_filtered::<Entity, With<PrimaryWindow>>()
+            .single(world)
+            .expect("headless window");
+        self.key(window, key_code, ButtonState::Pressed);
+        self.app.update();
+        self.key(window, key_code, ButtonState::Released);
+        self.app.update();
+    }
+
+    /// Types `text` one key per frame, the way the console sees it.
+    #[cfg(feature = "dev-tools")]
+    pub fn type_text(&mut self, text: &str) {
+        let world = self.world();
+        let window = world
+            .query_filtered::<Entity, With<PrimaryWindow>>()
+            .single(world)
+            .expect("headless window");
+        for character in text.chars() {
+            let (key_code, logical_key) = match character {
+                ' ' => (KeyCode::Space, Key::Space),
+                '\n' => (KeyCode::Enter, Key::Enter),
+                _ => (KeyCode::KeyA, Key::Character(character.to_string().into())),
+            };
+            self.world().send_event(KeyboardInput {
+                key_code,
+                logical_key,
+                state: ButtonState::Pressed,
+                text: None,
+                repeat: false,
+                window,
+            });
+            self.app.update();
+        }
+    }
+
+    fn key(&mut self, window: Entity, key_code: KeyCode, state: ButtonState) {
+        let logical_key = match key_code {
+            KeyCode::Space => Key::Space,
+            KeyCode::Backquote => Key::Character("`".into()),
+            KeyCode::Escape => Key::Escape,
+            KeyCode::KeyP => Key::Character("p".into()),
+            KeyCode::Digit1 => Key::Character("1".into()),
+            KeyCode::Digit7 => Key::Character("7".into()),
+            KeyCode::Backspace => Key::Backspace,
+            _ => Key::Unidentified(NativeKey::Unidentified),
+        };
+        self.world().send_event(KeyboardInput {
+            key_code,
+            logical_key,
+            state,
+            text: None,
+            repeat: false,
+            window,
+        });
+    }
+
+    /// Reports a contact between `a` and `b` the way rapier does.
+    pub fn collide(&mut self, a: Entity, b: Entity) {
+        self.world()
+            .send_event(CollisionEvent::Started(a, b, CollisionEventFlags::empty()));
+    }
+
+    /// A meteor at `position`, still and on screen. Health is 2000 × `scale`.
+    pub fn spawn_meteor(&mut self, position: Vec2, scale: f32) -> Entity {
+        let world = self.world();
+        let entity = enemy::spawn_meteor(
+            &mut world.commands(),
+            MeteorSpawn {
+                image: Handle::default(),
+                position,
+                direction: Vec2::X,
+                speed: 0.0,
+                rotation: 0.0,
+                scale,
+                entering: false,
+            },
+        );
+        world.flush();
+        entity
+    }
+
+    /// A player bullet parked at `position`, as if fired through the pool.
+    pub fn spawn_bullet(&mut self, position: Vec2) -> Entity {
+        self.world()
+            .spawn((
+                Transform::from_translation(position.extend(0.0)),
+                Bullet {
+                    speed: 0.0,
+                    direction: Vec3::X,
+                    lifetime: Timer::from_seconds(60.0, TimerMode::Once),
+                    range: f32::MAX,
+                    travelled: 0.0,
+                    script: None,
+                },
+                DamageDealer {
+                    amount: 50.0,
+                    kind: DamageKind::Kinetic,
+                },
+            ))
+            .id()
+    }
+
+    pub fn spawn_wall(&mut self) -> Entity {
+        self.world()
+            .spawn((Transform::default(), Wall, WallSide::Left))
+            .id()
+    }
+
+    pub fn count<F: QueryFilter>(&mut self) -> usize {
+        let world = self.world();
+        world.query_filtered::<(), F>().iter(world).count()
+    }
+
+    pub fn exists(&mut self, entity: Entity) -> bool {
+        self.world().get_entity(entity).is_ok()
+    }
+
+    pub fn has<C: Component>(&mut self, entity: Entity) -> bool {
+        self.world().get::<C>(entity).is_some()
+    }
+
+    pub fn score(&mut self) -> u32 {
+        let world = self.world();
+        world
+            .query::<&Score>()
+            .single(world)
+            .expect("one score")
+            .score
+    }
+
+    /// Shots fired and shots that hit.
+    pub fn accuracy(&mut self) -> (f32, f32) {
+        let world = self.world();
+        let accuracy = world
+            .query::<&Accuracy>()
+            .single(world)
+            .expect("one accuracy");
+        (accuracy.bullets_fired, accuracy.bullets_hit)
+    }
+
+    /// Text of the single entity carrying `C`, e.g. the score line.
+    pub fn text<C: Component>(&mut self) -> String {
+        let world = self.world();
+        world
+            .query_filtered::<&Text, With<C>>()
+            .single(world)
+            .expect("one text")
+            .0
+            .clone()
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowFocused};
+use std::ffi::OsString;
+
+use super::TestGame;
+use crate::components_and_resources::ConfigHandle;
+use crate::config::Config;
+use crate::pause::{self, PauseButton, PauseMenu, PauseState, SeedEntry};
+use crate::replay::{InputFrame, Playback, PlayerInput, Recording, ReplayMode};
+
+fn state(game: &mut TestGame) -> PauseState {
+    *game.world().resource::<State<PauseState>>().get()
+}
+
+fn press(game: &mut TestGame, button: PauseButton) {
+    let world = game.world();
+    let entity = world
+        .query::<(Entity, &PauseButton)>()
+        .iter(world)
+        .find(|(_, candidate)| **candidate == button)
+        .map(|(entity, _)| entity)
+        .expect("button on the menu");
+    world.entity_mut(entity).insert(Interaction::Pressed);
+    game.app.update();
+}
+
+#[test]
+fn escape_and_p_pause_and_resume() {
+    let mut game = TestGame::new();
+    game.tap(KeyCode::Escape);
+    assert_eq!(state(&mut game), PauseState::Paused);
+    assert!(game.world().resource::<Time<Virtual>>().is_paused());
+    assert_eq!(game.count::<With<PauseMenu>>(), 1);
+
+    game.tap(KeyCode::KeyP);
+    assert_eq!(state(&mut game), PauseState::Running);
+    assert!(!game.world().resource::<Time<Virtual>>().is_paused());
+    game.app.update();
+    assert_eq!(game.count::<With<PauseMenu>>(), 0);
+}
+
+#[test]
+fn nothing_moves_while_paused() {
+    let mut game = TestGame::new();
+    game.tap(KeyCode::KeyP);
+    let before = game.world().resource::<Time<Fixed>>().elapsed();
+    for _ in 0..30 {
+        game.app.update();
+    }
+    assert_eq!(game.world().resource::<Time<Fixed>>().elapsed(), before);
+}
+
+#[test]
+fn losing_focus_pauses() {
+    let mut game = TestGame::new();
+    let world = game.world();
+    let window = world
+        .query_filtered::<Entity, With<PrimaryWindow>>()
+        .single(world)
+        .unwrap();
+    world.send_event(WindowFocused {
+        window,
+        focused: false,
+    });
+    game.app.update();
+    game.app.update();
+    assert_eq!(state(&mut game), PauseState::Paused);
+}
+
+#[test]
+fn menu_resumes_changes_settings_and_quits() {
+    let mut game = TestGame::new();
+    game.tap(KeyCode::Escape);
+
+    press(&mut game, PauseButton::Settings);
+    press(&mut game, PauseButton::HitStop);
+    let world = game.world();
+    let handle = &world.resource::<ConfigHandle>().0;
+    let config = world.resource::<Assets<Config>>().get(handle).unwrap();
+    assert!(!config.settings.hit_stop);
+
+    press(&mut game, PauseButton::Back);
+    press(&mut game, PauseButton::Resume);
+    game.app.update();
+    assert_eq!(state(&mut game), PauseState::Running);
+
+    game.tap(KeyCode::Escape);
+    press(&mut game, PauseButton::Quit);
+    assert!(!game.world().resource::<Events<AppExit>>().is_empty());
+}
+
+#[test]
+fn replay_controls_wait_for_the_pause_menu() {
+    let mut game = TestGame::new();
+    game.world().insert_resource(ReplayMode::Playback(Playback {
+        recording: Recording {
+            frames: vec![InputFrame::from_input(&PlayerInput::default()); 1000],
+            ..default()
+        },
+        tick: 0,
+        speed: 2,
+        seek_to: None,
+    }));
+    game.tap(KeyCode::Escape);
+
+    game.tap(KeyCode::Space);
+    game.tap(KeyCode::ArrowRight);
+    assert_eq!(state(&mut game), PauseState::Paused);
+    assert!(game.world().resource::<Time<Virtual>>().is_paused());
+}
+
+#[test]
+fn seeds_can_be_tapped_and_typed() {
+    let mut game = TestGame::new();
+    game.tap(KeyCode::Escape);
+    press(&mut game, PauseButton::EnterSeed);
+
+    // nothing typed yet, so nothing to play
+    press(&mut game, PauseButton::PlaySeed);
+    assert!(game.world().resource::<SeedEntry>().note.is_some());
+
+    press(&mut game, PauseButton::Digit(4));
+    press(&mut game, PauseButton::Digit(2));
+    game.tap(KeyCode::Digit7);
+    game.tap(KeyCode::Backspace);
+    game.tap(KeyCode::Digit1);
+    let entry = game.world().resource::<SeedEntry>();
+    assert_eq!(entry.digits, "421");
+    assert!(entry.note.is_none());
+
+    press(&mut game, PauseButton::DeleteDigit);
+    assert_eq!(game.world().resource::<SeedEntry>().digits, "42");
+    press(&mut game, PauseButton::Back);
+    press(&mut game, PauseButton::Resume);
+    game.app.update();
+    assert_eq!(state(&mut game), PauseState::Running);
+}
+
+#[test]
+fn restarts_record_to_a_new_file() {
+    let dir = std::env::temp_dir().join(format!("advent-restart-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let saved = dir.join("run.replay");
+    for taken in ["run.replay", "run-1.replay"] {
+        std::fs::write(dir.join(taken), b"").unwrap();
+    }
+
+    let args: Vec<OsString> = vec![
+        "--seed".into(),
+        "5".into(),
+        "--record".into(),
+        saved.clone().into(),
+    ];
+    let relaunched = pause::record_to_new_file(args);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        relaunched,
+        vec![
+            OsString::from("--seed"),
+            "5".into(),
+            "--record".into(),
+            dir.join("run-2.replay").into(),
+        ]
+    );
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+use crate::config::Spawning;
+use crate::replay::{InputFrame, PlayerInput, Recording};
+
+fn recording() -> Recording {
+    let inputs = [
+        PlayerInput::default(),
+        PlayerInput {
+            aim: 1.0,
+            fire: true,
+            movement: Vec2::new(1.0, -1.0),
+        },
+        PlayerInput {
+            aim: -2.5,
+            fire: false,
+            movement: Vec2::new(-1.0, 1.0),
+        },
+    ];
+    Recording {
+        seed: 0xDEAD_BEEF_1234,
+        arena: Vec2::new(1920.0, 1080.0),
+        spawning: Some(Spawning {
+            safe_radius: 123.0,
+            ..default()
+        }),
+        frames: inputs.iter().map(InputFrame::from_input).collect(),
+    }
+}
+
+#[test]
+fn recordings_survive_encoding() {
+    let recording = recording();
+    let decoded = Recording::decode(&recording.encode()).unwrap();
+    assert_eq!(decoded, recording);
+
+    let without_settings = Recording {
+        spawning: None,
+        ..recording
+    };
+    let decoded = Recording::decode(&without_settings.encode()).unwrap();
+    assert_eq!(decoded, without_settings);
+}
+
+#[test]
+fn quantised_input_comes_back_the_same() {
+    let frame = InputFrame::from_input(&PlayerInput {
+        aim: 1.0,
+        fire: true,
+        movement: Vec2::new(1.0, -1.0),
+    });
+    let input = frame.input();
+    assert!((input.aim - 1.0).abs() < 1e-3);
+    assert!(input.fire);
+    assert_eq!(input.movement, Vec2::new(1.0, -1.0));
+    assert_eq!(InputFrame::from_input(&input), frame);
+}
+
+#[test]
+fn broken_recordings_are_refused() {
+    let bytes = recording().encode();
+    assert!(Recording::decode(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Recording::decode(&bytes[..10]).is_err());
+    assert!(Recording::decode(b"not a replay at all, just some text").is_err());
+
+    // a frame count this big overflows the byte count on 32-bit targets
+    let mut huge = bytes.clone();
+    huge[21..25].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(Recording::decode(&huge).is_err());
+
+    let mut newer = bytes;
+    newer[4] += 1;
+    assert!(Recording::decode(&newer).is_err());
+}
@@ -1,16 +1,18 @@
 use crate::components_and_resources::{HitSoundBulletMeteor, Smoke};
+use crate::pool::EntityPool;
 use bevy::audio::Volume;
 use bevy::prelude::*;
 use std::path::PathBuf;
 
 pub fn spawn_sprite(
     commands: &mut Commands,
+    pool: &mut EntityPool<Smoke>,
     asset_server: AssetServer,
     sprite_asset_path: PathBuf,
     transform: Transform,
 ) -> Entity {
-    commands
-        .spawn((
+    pool.acquire(commands)
+        .insert((
             Sprite::from_image(asset_server.load(sprite_asset_path)),
             transform,
             Smoke {
@@ -22,13 +24,14 @@ pub fn spawn_sprite(
 
 pub fn spawn_audio(
     commands: &mut Commands,
+    pool: &mut EntityPool<HitSoundBulletMeteor>,
     asset_server: AssetServer,
     sound_asset_path: PathBuf,
     volume: f32,
     duration: f32,
 ) -> Entity {
-    commands
-        .spawn((
+    pool.acquire(commands)
+        .insert((
             AudioPlayer::new(asset_server.load(sound_asset_path)),
             PlaybackSettings::ONCE.with_volume(Volume::Linear(volume)),
             HitSoundBulletMeteor {
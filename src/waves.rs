@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+
+use crate::boss::Boss;
+use crate::components_and_resources::{Enemy, Level};
+use crate::console::{self, AddConsoleCommand, ConsoleReply};
+use crate::damage::DeathEvent;
+use crate::schedule::GameSet;
+
+// the run is split into waves of meteors, every BOSS_EVERY-th wave is a boss fight instead.
+// Beating a boss moves on to the next level.
+
+const KILLS_PER_WAVE: u32 = 12;
+const BOSS_EVERY: u32 = 3;
+
+#[derive(Resource, Debug)]
+pub struct Waves {
+    /// Current wave, starting at 1.
+    pub number: u32,
+    /// Meteors destroyed in the current wave.
+    pub kills: u32,
+    /// The boss being fought, meteors stop spawning while it's alive.
+    pub boss: Option<Entity>,
+}
+
+impl Default for Waves {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            kills: 0,
+            boss: None,
+        }
+    }
+}
+
+impl Waves {
+    pub fn is_boss_wave(&self) -> bool {
+        self.number.is_multiple_of(BOSS_EVERY)
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WaveStarted {
+    pub number: u32,
+    pub boss: bool,
+}
+
+pub fn advance_waves(
+    mut deaths: EventReader<DeathEvent>,
+    q_enemy: Query<Has<Boss>, With<Enemy>>,
+    mut waves: ResMut<Waves>,
+    mut level: ResMut<Level>,
+    mut started: EventWriter<WaveStarted>,
+) {
+    for death in deaths.read() {
+        let Ok(is_boss) = q_enemy.get(death.entity) else {
+            continue;
+        };
+        if is_boss {
+            waves.boss = None;
+            level.0 += 1;
+        } else if !waves.is_boss_wave() {
+            waves.kills += 1;
+            if waves.kills < KILLS_PER_WAVE {
+                continue;
+            }
+        } else {
+            // stragglers from the previous wave dying during a boss fight
+            continue;
+        }
+
+        waves.number += 1;
+        waves.kills = 0;
+        info!("wave {} started", waves.number);
+        started.write(WaveStarted {
+            number: waves.number,
+            boss: waves.is_boss_wave(),
+        });
+    }
+}
+
+fn set_wave(
+    In(args): In<Vec<String>>,
+    mut waves: ResMut<Waves>,
+    mut started: EventWriter<WaveStarted>,
+) -> ConsoleReply {
+    let number: u32 = console::arg(&args, 0, "wave number")?;
+    if number == 0 {
+        return Err("waves start at 1".to_string());
+    }
+    waves.number = number;
+    waves.kills = 0;
+    let boss = waves.is_boss_wave();
+    started.write(WaveStarted { number, boss });
+    Ok(format!(
+        "wave {number}{}",
+        if boss { ", a boss wave" } else { "" }
+    ))
+}
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Waves>()
+            .add_event::<WaveStarted>()
+            .add_systems(
+                FixedUpdate,
+                advance_waves
+                    .after(crate::damage::apply_damage)
+                    .before(crate::damage::run_death_hooks)
+                    .in_set(GameSet::Collision),
+            )
+            .add_run_command("wave", "wave <number>", set_wave);
+    }
+}